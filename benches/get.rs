@@ -0,0 +1,125 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_json::{json, Value};
+use tailcall_lens::{Lens, LensCache};
+
+fn nested(depth: usize) -> Value {
+    (0..depth).fold(json!(42), |value, _| json!([0, value]))
+}
+
+fn index_chain(depth: usize) -> Lens {
+    (0..depth).fold(Lens::default(), |lens, _| lens.select(1))
+}
+
+fn bench_index_chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("index_chain");
+    for depth in [4, 16, 64] {
+        let value = nested(depth);
+        let hit = index_chain(depth);
+        let miss = index_chain(depth).select(0);
+        group.bench_with_input(BenchmarkId::new("hit", depth), &depth, |b, _| {
+            b.iter(|| black_box(hit.get(black_box(&value))))
+        });
+        group.bench_with_input(BenchmarkId::new("miss", depth), &depth, |b, _| {
+            b.iter(|| black_box(miss.get(black_box(&value))))
+        });
+    }
+    group.finish();
+}
+
+fn bench_for_each(c: &mut Criterion) {
+    let mut group = c.benchmark_group("for_each");
+    for len in [16, 256, 4096] {
+        let value = Value::Array((0..len).map(|i| json!({ "id": i })).collect());
+        let lens = Lens::foreach().select("id");
+        group.bench_with_input(BenchmarkId::new("get", len), &len, |b, _| {
+            b.iter(|| black_box(lens.get(black_box(&value))))
+        });
+        let mut buf = Vec::new();
+        group.bench_with_input(BenchmarkId::new("get_into", len), &len, |b, _| {
+            b.iter(|| black_box(lens.get_into(black_box(&value), &mut buf)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_compiled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compiled");
+    let value = json!({"config": {"a": 1, "Name": "x"}});
+    let field_ci = |field: &str| Lens::FieldCI(field.to_string());
+    let chains = [
+        ("field", Lens::new("config").select("a")),
+        (
+            "field_ci",
+            Lens::new(field_ci("CONFIG")).select(field_ci("name")),
+        ),
+    ];
+    for (name, lens) in chains {
+        let compiled = lens.compile();
+        group.bench_function(BenchmarkId::new("raw", name), |b| {
+            b.iter(|| black_box(lens.get(black_box(&value))))
+        });
+        group.bench_function(BenchmarkId::new("compiled", name), |b| {
+            b.iter(|| black_box(compiled.get(black_box(&value))))
+        });
+    }
+    for depth in [4, 16, 64] {
+        let value = nested(depth);
+        let lens = index_chain(depth);
+        let compiled = lens.compile();
+        group.bench_with_input(BenchmarkId::new("raw", depth), &depth, |b, _| {
+            b.iter(|| black_box(lens.get(black_box(&value))))
+        });
+        group.bench_with_input(BenchmarkId::new("compiled", depth), &depth, |b, _| {
+            b.iter(|| black_box(compiled.get(black_box(&value))))
+        });
+    }
+    group.finish();
+}
+
+fn bench_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cache");
+    for width in [16, 256, 4096] {
+        let fields = |prefix: &str| {
+            (0..width)
+                .map(|i| (format!("{}{}", prefix, i), json!(i)))
+                .collect::<serde_json::Map<_, _>>()
+        };
+        let mut inner = fields("field");
+        let mut outer = fields("key");
+        inner.insert("id".to_string(), json!(1));
+        inner.insert("name".to_string(), json!("a"));
+        outer.insert("user".to_string(), Value::Object(inner));
+        let value = json!({ "data": Value::Object(outer) });
+        let user = Lens::FieldCI("USER".to_string());
+        let lenses: Vec<_> = ["id", "name", "missing"]
+            .into_iter()
+            .map(|field| Lens::new("data").select(user.clone()).select(field))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("uncached", width), &width, |b, _| {
+            b.iter(|| {
+                for lens in &lenses {
+                    black_box(lens.get(black_box(&value)));
+                }
+            })
+        });
+        let mut cache = LensCache::new(&value);
+        group.bench_with_input(BenchmarkId::new("cached", width), &width, |b, _| {
+            b.iter(|| {
+                for lens in &lenses {
+                    black_box(cache.get(lens));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_index_chain,
+    bench_for_each,
+    bench_compiled,
+    bench_cache
+);
+criterion_main!(benches);
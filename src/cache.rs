@@ -0,0 +1,127 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use serde_json::Value;
+
+use crate::lens::{is_step, step};
+use crate::{Lens, View};
+
+/// A cached prefix and the container it leads to, `None` if it misses.
+type Entry<'a> = (Vec<Lens>, Option<&'a Value>);
+
+/// Memoizes path resolution for many lenses applied to one document. Each
+/// lens's leading run of `Field`, `FieldCI` and `Index` segments is split
+/// before its last segment, and the container that prefix leads to is
+/// cached, so sibling lenses like `a.b.c` and `a.b.d` resolve `a.b` only
+/// once. The rest of the lens is applied to the cached container as usual.
+///
+/// The document is borrowed immutably for the cache's lifetime, so cached
+/// entries can't go stale.
+#[derive(Debug)]
+pub struct LensCache<'a> {
+    root: &'a Value,
+    /// Cached prefixes and their containers, bucketed by the hash of the
+    /// prefix so that a lookup doesn't need to build an owned key.
+    containers: HashMap<u64, Vec<Entry<'a>>>,
+}
+
+impl<'a> LensCache<'a> {
+    pub fn new(root: &'a Value) -> Self {
+        LensCache {
+            root,
+            containers: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &'a Value {
+        self.root
+    }
+
+    /// Same as `lens.get(root)`, reusing and filling the cache.
+    pub fn get<'b>(&mut self, lens: &'b Lens) -> Option<View<'b>>
+    where
+        'a: 'b,
+    {
+        let parts = lens.parts();
+        let run = parts.iter().take_while(|part| is_step(part)).count();
+        if run == 0 {
+            return lens.get(self.root);
+        }
+        let (prefix, rest) = parts.split_at(run - 1);
+        let mut view = View::Borrow(self.container(prefix)?);
+        for part in rest {
+            view = view.get(part)?;
+        }
+        Some(view)
+    }
+
+    fn container(&mut self, prefix: &[&Lens]) -> Option<&'a Value> {
+        let mut hasher = DefaultHasher::new();
+        for part in prefix {
+            hash_step(part, &mut hasher);
+        }
+        let bucket = self.containers.entry(hasher.finish()).or_default();
+        if let Some((_, container)) = bucket
+            .iter()
+            .find(|(cached, _)| cached.iter().eq(prefix.iter().copied()))
+        {
+            return *container;
+        }
+        let container = prefix
+            .iter()
+            .try_fold(self.root, |value, part| step(part, value));
+        bucket.push((
+            prefix.iter().map(|part| (*part).clone()).collect(),
+            container,
+        ));
+        container
+    }
+}
+
+fn hash_step(part: &Lens, hasher: &mut DefaultHasher) {
+    match part {
+        Lens::Field(field) => (0u8, field).hash(hasher),
+        Lens::FieldCI(field) => (1u8, field).hash(hasher),
+        Lens::Index(index) => (2u8, index).hash(hasher),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let value =
+            json!({"data": {"user": {"id": 1, "tags": ["a", "b"]}, "items": [{"n": 1}, {"n": 2}]}});
+        let lenses = [
+            Lens::new("data").select("user").select("id"),
+            Lens::new("data")
+                .select("user")
+                .select("tags")
+                .select(Lens::LAST),
+            Lens::new("data").select("user").select("missing"),
+            Lens::new("data")
+                .select(Lens::FieldCI("USER".to_string()))
+                .select("id"),
+            Lens::new("data").select("items").each().select("n"),
+            Lens::new("data").select("items").select(Lens::Empty),
+            Lens::new("data"),
+            Lens::Empty,
+            Lens::new(Lens::Wildcard).select("user"),
+            Lens::new("missing").select("user").select("id"),
+        ];
+        let mut cache = LensCache::new(&value);
+        for _ in 0..2 {
+            for lens in &lenses {
+                assert_eq!(cache.get(lens), lens.get(&value), "{}", lens);
+            }
+        }
+        let cached: usize = cache.containers.values().map(Vec::len).sum();
+        assert_eq!(cached, 6);
+    }
+}
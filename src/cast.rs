@@ -0,0 +1,74 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+use crate::{LensErrorKind, View};
+
+/// The scalar type a [`Lens::Cast`](crate::Lens::Cast) coerces its focus to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsonType {
+    Bool,
+    Number,
+    String,
+}
+
+impl JsonType {
+    pub fn name(self) -> &'static str {
+        match self {
+            JsonType::Bool => "bool",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+        }
+    }
+
+    /// Converts `value` to this type: strings parse as numbers or as `true`
+    /// and `false`, numbers and bools render as strings, and a value that
+    /// already has the type is kept as is. Anything else fails.
+    pub fn coerce(self, value: &Value) -> Result<Value, LensErrorKind> {
+        if self.matches(value) {
+            return Ok(value.clone());
+        }
+        let coerced = match (self, value) {
+            (JsonType::Bool, Value::String(text)) => text.parse().ok().map(Value::Bool),
+            (JsonType::Number, Value::String(text)) => {
+                text.trim().parse::<Number>().ok().map(Value::Number)
+            }
+            (JsonType::String, Value::Number(number)) => Some(Value::String(number.to_string())),
+            (JsonType::String, Value::Bool(bool)) => Some(Value::String(bool.to_string())),
+            _ => None,
+        };
+        coerced.ok_or_else(|| LensErrorKind::mismatch(self.name(), value))
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (JsonType::Bool, Value::Bool(_))
+                | (JsonType::Number, Value::Number(_))
+                | (JsonType::String, Value::String(_))
+        )
+    }
+
+    /// Coerces every leaf of `view`, borrowing those that already have the
+    /// type. Leaves of a multi-focus that can't be coerced are skipped.
+    pub(crate) fn cast_view<'a>(self, view: View<'a>) -> Result<View<'a>, LensErrorKind> {
+        match view {
+            View::Borrow(value) if self.matches(value) => Ok(View::Borrow(value)),
+            View::Borrow(value) => self.coerce(value).map(View::Owned),
+            View::Owned(value) => self.coerce(&value).map(View::Owned),
+            View::BorrowVec(views) => Ok(View::BorrowVec(
+                views
+                    .into_iter()
+                    .filter_map(|view| self.cast_view(view).ok())
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for JsonType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,42 @@
+use serde_json::Value;
+
+use crate::LensErrorKind;
+
+/// Parses a JSON document embedded in a string focus.
+pub(crate) fn decode_json(value: &Value) -> Result<Value, LensErrorKind> {
+    match value {
+        Value::String(text) => {
+            serde_json::from_str(text).map_err(|err| LensErrorKind::Decode(err.to_string()))
+        }
+        value => Err(LensErrorKind::mismatch("string", value)),
+    }
+}
+
+pub(crate) fn encode_json(value: &Value) -> Value {
+    Value::String(value.to_string())
+}
+
+/// Decodes a base64 string focus holding a UTF-8 encoded JSON document.
+#[cfg(feature = "base64")]
+pub(crate) fn decode_base64(value: &Value) -> Result<Value, LensErrorKind> {
+    use base64::Engine;
+
+    match value {
+        Value::String(text) => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|err| LensErrorKind::Decode(err.to_string()))?;
+            let text =
+                String::from_utf8(bytes).map_err(|err| LensErrorKind::Decode(err.to_string()))?;
+            serde_json::from_str(&text).map_err(|err| LensErrorKind::Decode(err.to_string()))
+        }
+        value => Err(LensErrorKind::mismatch("string", value)),
+    }
+}
+
+#[cfg(feature = "base64")]
+pub(crate) fn encode_base64(value: &Value) -> Value {
+    use base64::Engine;
+
+    Value::String(base64::engine::general_purpose::STANDARD.encode(value.to_string()))
+}
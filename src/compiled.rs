@@ -0,0 +1,176 @@
+use serde_json::Value;
+
+use crate::lens::{find_lowered, is_step, step, step_mut};
+use crate::{Lens, Modify, View};
+
+/// A lens prepared by [`Lens::compile`] for repeated application. Pure
+/// `Field`/`FieldCI`/`Index` chains are lowered to a flat list of steps that
+/// is walked in a loop, without recursing through `Compose` nodes, and the
+/// keys of `FieldCI` steps are lowercased once up front; any other lens is
+/// applied as is.
+///
+/// Objects are B-tree maps (or insertion-ordered maps with `preserve_order`),
+/// so a key lookup compares strings rather than hashing them and there are
+/// no hashes to precompute.
+#[derive(Debug, PartialEq)]
+pub struct CompiledLens {
+    lens: Lens,
+    steps: Option<Vec<Step>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// A `Field` or `Index`, resolved by the shared segment step.
+    Segment(Lens),
+    /// A `FieldCI`, keeping the key as given for inserts.
+    FieldCI { field: String, lowered: String },
+}
+
+impl Lens {
+    pub fn compile(&self) -> CompiledLens {
+        let parts = self.parts();
+        let steps = parts
+            .iter()
+            .all(|part| is_step(part))
+            .then(|| parts.into_iter().map(Step::new).collect());
+        CompiledLens {
+            lens: self.clone(),
+            steps,
+        }
+    }
+}
+
+impl Step {
+    fn new(part: &Lens) -> Self {
+        match part {
+            Lens::FieldCI(field) => Step::FieldCI {
+                field: field.clone(),
+                lowered: field.to_lowercase(),
+            },
+            part => Step::Segment(part.clone()),
+        }
+    }
+
+    fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        match (self, value) {
+            (Step::Segment(part), value) => step(part, value),
+            (Step::FieldCI { lowered, .. }, Value::Object(obj)) => {
+                obj.get(find_lowered(obj, lowered)?)
+            }
+            (Step::FieldCI { .. }, _) => None,
+        }
+    }
+
+    fn get_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        match (self, value) {
+            (Step::Segment(part), value) => step_mut(part, value),
+            (Step::FieldCI { lowered, .. }, Value::Object(obj)) => {
+                let key = find_lowered(obj, lowered)?.clone();
+                obj.get_mut(&key)
+            }
+            (Step::FieldCI { .. }, _) => None,
+        }
+    }
+
+    fn set(&self, parent: &mut Value, target: Value) {
+        match (self, parent) {
+            (Step::Segment(part), parent) => part.set(parent, target),
+            (Step::FieldCI { field, lowered }, Value::Object(obj)) => {
+                let key = find_lowered(obj, lowered).unwrap_or(field).clone();
+                obj.insert(key, target);
+            }
+            (Step::FieldCI { .. }, _) => {}
+        }
+    }
+}
+
+impl CompiledLens {
+    pub fn lens(&self) -> &Lens {
+        &self.lens
+    }
+
+    pub fn get<'a>(&'a self, value: &'a Value) -> Option<View<'a>> {
+        let Some(steps) = &self.steps else {
+            return self.lens.get(value);
+        };
+        steps
+            .iter()
+            .try_fold(value, |value, step| step.get(value))
+            .map(View::Borrow)
+    }
+
+    pub fn get_mut<'a>(&'a self, value: &'a mut Value) -> Option<Modify<'a>> {
+        let Some(steps) = &self.steps else {
+            return self.lens.get_mut(value);
+        };
+        descend(steps, value).map(Modify::BorrowMut)
+    }
+
+    pub fn set(&self, source: &mut Value, target: Value) {
+        let Some((last, parents)) = self.steps.as_ref().and_then(|s| s.split_last()) else {
+            return self.lens.set(source, target);
+        };
+        if let Some(parent) = descend(parents, source) {
+            last.set(parent, target);
+        }
+    }
+}
+
+fn descend<'a>(steps: &[Step], value: &'a mut Value) -> Option<&'a mut Value> {
+    steps
+        .iter()
+        .try_fold(value, |value, step| step.get_mut(value))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_compiled() {
+        let mut value = json!({"config": {"a": [1, 2]}});
+        let lens = Lens::new("config").select("a").select(Lens::LAST).compile();
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!(2))));
+
+        lens.set(&mut value, json!(3));
+        assert_eq!(value, json!({"config": {"a": [1, 3]}}));
+        if let Some(Modify::BorrowMut(leaf)) = lens.get_mut(&mut value) {
+            *leaf = json!(4);
+        }
+        assert_eq!(value, json!({"config": {"a": [1, 4]}}));
+        assert_eq!(lens.get(&json!({"config": {}})), None);
+    }
+
+    #[test]
+    fn test_compiled_fallback() {
+        let mut value = json!({"items": [{"a": 1}, {"a": 2}]});
+        let lens = Lens::new("items").each().select("a").compile();
+        assert_eq!(lens.get(&value), lens.lens().get(&value));
+
+        lens.set(&mut value, json!(0));
+        assert_eq!(value, json!({"items": [{"a": 0}, {"a": 0}]}));
+    }
+
+    #[test]
+    fn test_compiled_field_ci() {
+        let mut value = json!({"Config": {"Name": "a"}});
+        let lens = Lens::new(Lens::FieldCI("config".to_string()))
+            .select(Lens::FieldCI("name".to_string()))
+            .compile();
+        assert!(lens.steps.is_some());
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!("a"))));
+
+        lens.set(&mut value, json!("b"));
+        assert_eq!(value, json!({"Config": {"Name": "b"}}));
+        assert_eq!(lens.get(&value), lens.lens().get(&value));
+
+        let lens = Lens::new("Config")
+            .select(Lens::FieldCI("Missing".to_string()))
+            .compile();
+        lens.set(&mut value, json!(1));
+        assert_eq!(value, json!({"Config": {"Name": "b", "Missing": 1}}));
+        assert_eq!(lens.get(&json!({"Config": [1]})), None);
+    }
+}
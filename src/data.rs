@@ -0,0 +1,123 @@
+use serde_json::Value;
+
+use crate::lens::{array_index, is_step};
+use crate::Lens;
+
+/// A document type other than `serde_json::Value` that lenses can run over.
+/// `Field`, `FieldCI` and `Index` steps are resolved on the document itself,
+/// so nodes away from the focus are never touched. The rest of a lens is
+/// applied to a JSON copy of the node those steps lead to, which is
+/// converted back afterwards.
+pub(crate) trait Data: Clone {
+    /// Applies a single `Field`, `FieldCI` or `Index`, like `lens::step`.
+    fn step(&self, part: &Lens) -> Option<&Self>;
+
+    /// Like [`Data::step`], borrowing mutably.
+    fn step_mut(&mut self, part: &Lens) -> Option<&mut Self>;
+
+    /// Writes `value` through a single step, like `part.set(self, value)`.
+    fn set_step(&mut self, part: &Lens, value: Self);
+
+    /// Removes the child a single step leads to, like `part.delete(self)`.
+    fn delete_step(&mut self, part: &Lens);
+
+    fn to_json(&self) -> Option<Value>;
+
+    fn from_json(value: &Value) -> Option<Self>;
+}
+
+/// The first key equal to `field` ignoring case, as `FieldCI` matches it.
+pub(crate) fn find_key_ci<'a, I: IntoIterator<Item = &'a str>>(
+    keys: I,
+    field: &str,
+) -> Option<&'a str> {
+    let field = field.to_lowercase();
+    keys.into_iter().find(|key| key.to_lowercase() == field)
+}
+
+/// The position an `Index` step selects in a sequence of `len` elements.
+pub(crate) fn position(index: usize, len: usize) -> Option<usize> {
+    let index = array_index(index, len);
+    (index < len).then_some(index)
+}
+
+/// Splits the lens into its leading steps and the lens made of the rest,
+/// `None` if there is no rest.
+fn split(lens: &Lens) -> (Vec<&Lens>, Option<Lens>) {
+    let mut parts = lens.parts();
+    let run = parts.iter().take_while(|part| is_step(part)).count();
+    let rest = parts.split_off(run);
+    let rest =
+        (!rest.is_empty()).then(|| rest.into_iter().cloned().fold(Lens::Empty, Lens::select));
+    (parts, rest)
+}
+
+fn descend<'a, T: Data>(steps: &[&Lens], value: &'a T) -> Option<&'a T> {
+    steps.iter().try_fold(value, |value, part| value.step(part))
+}
+
+fn descend_mut<'a, T: Data>(steps: &[&Lens], value: &'a mut T) -> Option<&'a mut T> {
+    steps
+        .iter()
+        .try_fold(value, |value, part| value.step_mut(part))
+}
+
+/// Applies `f` to a JSON copy of `node` and writes the result back, leaving
+/// `node` as it is if either conversion fails.
+fn update_json<T: Data, F: FnOnce(&mut Value)>(node: &mut T, f: F) {
+    let Some(mut value) = node.to_json() else {
+        return;
+    };
+    f(&mut value);
+    if let Some(value) = T::from_json(&value) {
+        *node = value;
+    }
+}
+
+pub(crate) fn get<T: Data>(lens: &Lens, value: &T) -> Option<T> {
+    let (steps, rest) = split(lens);
+    let node = descend(&steps, value)?;
+    match rest {
+        None => Some(node.clone()),
+        Some(rest) => T::from_json(&rest.get(&node.to_json()?)?.into_value()),
+    }
+}
+
+pub(crate) fn set<T: Data>(lens: &Lens, source: &mut T, target: T) {
+    let (mut steps, rest) = split(lens);
+    match rest {
+        None => match steps.pop() {
+            Some(last) => {
+                if let Some(parent) = descend_mut(&steps, source) {
+                    parent.set_step(last, target);
+                }
+            }
+            None => *source = target,
+        },
+        Some(rest) => {
+            let (Some(node), Some(target)) = (descend_mut(&steps, source), target.to_json()) else {
+                return;
+            };
+            update_json(node, |value| rest.set(value, target));
+        }
+    }
+}
+
+pub(crate) fn delete<T: Data>(lens: &Lens, source: &mut T) {
+    let (mut steps, rest) = split(lens);
+    match rest {
+        None => match steps.pop() {
+            Some(last) => {
+                if let Some(parent) = descend_mut(&steps, source) {
+                    parent.delete_step(last);
+                }
+            }
+            None => update_json(source, |value| Lens::Empty.delete(value)),
+        },
+        Some(rest) => {
+            if let Some(node) = descend_mut(&steps, source) {
+                update_json(node, |value| rest.delete(value));
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// A failed lens operation. `path` renders the lens up to and including the
+/// segment that failed, e.g. `a.b[3]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LensError {
+    pub path: String,
+    pub kind: LensErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LensErrorKind {
+    MissingField(String),
+    IndexOutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    Decode(String),
+    Unsupported(&'static str),
+    /// The condition of a [`Lens::Guard`](crate::Lens::Guard) didn't hold.
+    GuardFailed,
+    /// No array element matched a [`Lens::ById`](crate::Lens::ById).
+    NoMatch,
+}
+
+impl LensError {
+    /// Prefixes the path with the rendering of the lens that was applied
+    /// before the failing one.
+    pub(crate) fn within(mut self, prefix: impl fmt::Display) -> Self {
+        self.path = join_path(&prefix.to_string(), &self.path);
+        self
+    }
+
+    /// Wraps the path in the `name(...)` of the decoding segment, such as
+    /// `embedded`, the failure happened inside of.
+    pub(crate) fn inside(mut self, name: &str) -> Self {
+        self.path = format!("{}({})", name, self.path);
+        self
+    }
+}
+
+impl LensErrorKind {
+    pub(crate) fn mismatch(expected: &'static str, found: &Value) -> Self {
+        LensErrorKind::TypeMismatch {
+            expected,
+            found: type_name(found),
+        }
+    }
+}
+
+impl fmt::Display for LensError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at `{}`", self.kind, self.path)
+    }
+}
+
+impl fmt::Display for LensErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LensErrorKind::MissingField(field) => write!(f, "missing field `{}`", field),
+            LensErrorKind::IndexOutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "index {} out of bounds for array of length {}",
+                    index, len
+                )
+            }
+            LensErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            LensErrorKind::Decode(message) => write!(f, "failed to decode focus: {}", message),
+            LensErrorKind::Unsupported(operation) => {
+                write!(f, "unsupported operation: {}", operation)
+            }
+            LensErrorKind::GuardFailed => write!(f, "guard condition not met"),
+            LensErrorKind::NoMatch => write!(f, "no matching element"),
+        }
+    }
+}
+
+impl std::error::Error for LensError {}
+
+/// Returned by [`Lens::get_capped`](crate::Lens::get_capped) when a lens
+/// focuses more than `max` leaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapExceeded {
+    pub max: usize,
+}
+
+impl fmt::Display for CapExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "lens focuses more than {} values", self.max)
+    }
+}
+
+impl std::error::Error for CapExceeded {}
+
+/// Joins two rendered paths, omitting the `.` before an index or when either
+/// side is the empty root path.
+pub(crate) fn join_path(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() || path.is_empty() || path.starts_with('[') {
+        format!("{}{}", prefix, path)
+    } else {
+        format!("{}.{}", prefix, path)
+    }
+}
+
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
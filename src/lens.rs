@@ -1,230 +1,4580 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Number, Value};
 
-use crate::{Modify, Select, View};
+#[cfg(feature = "base64")]
+use crate::codec::{decode_base64, encode_base64};
+use crate::codec::{decode_json, encode_json};
+use crate::error::{join_path, type_name};
+use crate::op::{fill_defaults, merge_patch};
+use crate::{
+    ArrayChange, CapExceeded, Change, JsonType, LensError, LensErrorKind, LensOp, Modify, OpResult,
+    Operation, OwnedView, Segment, Select, SelectError, TrySelect, View,
+};
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+/// A path into a JSON document. New kinds of segments are added over time,
+/// so matches outside this crate need a wildcard arm:
+///
+/// ```
+/// use tailcall_lens::Lens;
+///
+/// fn describe(lens: &Lens) -> String {
+///     match lens {
+///         Lens::Field(name) => format!("field {name}"),
+///         Lens::Index(index) => format!("index {index}"),
+///         _ => format!("lens {lens}"),
+///     }
+/// }
+///
+/// assert_eq!(describe(&Lens::new("a")), "field a");
+/// assert_eq!(describe(&Lens::new(0)), "index 0");
+/// assert_eq!(describe(&Lens::new("a").each()), "lens a[*]");
+/// ```
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum Lens {
     Field(String),
+    /// Like `Field`, but matches keys case-insensitively. If several keys
+    /// match, the first in iteration order wins. `set` writes to the matched
+    /// key, or inserts the given spelling if none matches.
+    FieldCI(String),
+    /// An array element. [`Lens::LAST`] denotes the last element, which an
+    /// empty array doesn't have.
     Index(usize),
     Compose(Box<Lens>, Box<Lens>),
     ForEach,
+    /// Like `ForEach`, but only over the first `n` elements (or all of them
+    /// if the array is shorter). Since the window is already iterated, a
+    /// following `select` applies to each element, no `each()` needed.
+    Take(usize),
+    /// Like `ForEach`, but over every element after the first `n`.
+    Skip(usize),
+    /// Like `ForEach`, but over the values of an object, in key order.
+    Wildcard,
+    /// The first element of an array whose `field` equals `value`, such as
+    /// `{"id": "b"}` in `[{"id": "a"}, {"id": "b"}]`. `set` replaces it and
+    /// `delete` removes it; if no element matches, `get` misses and `set`
+    /// and `delete` leave the array untouched.
+    ById {
+        field: String,
+        value: Value,
+    },
+    /// Like `ById`, for every id in `values`: a multi-focus of the matching
+    /// elements, in the order the ids are given. Ids that match nothing are
+    /// skipped, and an element matched by several ids is focused once.
+    ByIds {
+        field: String,
+        values: Vec<Value>,
+    },
+    /// Applies a function to every leaf focused by the inner lens, see
+    /// [`Lens::map`]. `get` yields the results as owned values, while `set`
+    /// and `delete` go through the inner lens unchanged, as the function
+    /// can't be inverted. `get_mut` is unsupported, and a lens holding a
+    /// function can't be serialized.
+    #[serde(skip)]
+    Map(Box<Lens>, MapFn),
+    /// The length of the focused array, displayed and parsed as `#`. `get`
+    /// yields it as an owned number and `set` resizes the array to the given
+    /// length, truncating it or padding it with `null`, and fails if the
+    /// padding can't be allocated. As with `Cast`, `get_mut` and `delete` are
+    /// unsupported.
+    Length,
+    /// Like `Wildcard`, but only over the keys matching a glob pattern, in
+    /// which `*` matches any run of characters and `?` a single one. `set`
+    /// only writes to keys that already match.
+    Glob(String),
+    /// Focuses the first sub-lens that resolves to a non-null value, falling
+    /// back to the first one resolving to `null`. `set` writes to the first
+    /// sub-lens that resolves at all, or to the first one if none does.
+    Coalesce(Vec<Lens>),
+    /// Focuses every sub-lens that resolves, in the order they were given,
+    /// splicing the elements of a multi-focus sub-lens in place. `set`
+    /// writes to each of them and `delete` removes them from last to first
+    /// so that ascending indices stay valid. `get_mut` is only supported for
+    /// unions of sibling `Field`s or sibling `Index`es, whose borrows are
+    /// disjoint.
+    Union(Vec<Lens>),
+    /// Parses the focused string as JSON and applies the inner lens to the
+    /// parsed document; `set` serializes the document back into the string.
+    /// A string that isn't valid JSON misses on `get` and is left untouched
+    /// by `set`. Since the parsed document is a temporary, `get` yields an
+    /// owned view and `get_mut` is unsupported, so further segments belong
+    /// inside the inner lens rather than after it.
+    Embedded(Box<Lens>),
+    /// Like `Embedded`, but the focused string holds base64 encoded UTF-8
+    /// JSON. Bad base64, invalid UTF-8 and invalid JSON all miss on `get`
+    /// and are left untouched by `set`.
+    #[cfg(feature = "base64")]
+    Base64(Box<Lens>),
+    /// Coerces the focus of the inner lens to a scalar type, see
+    /// [`JsonType::coerce`]. `get` yields the coerced value, missing on a
+    /// leaf that can't be coerced, and `set` coerces the incoming value
+    /// before writing it, leaving the document untouched if it can't be.
+    /// As with `Embedded`, `get_mut` is unsupported.
+    Cast(Box<Lens>, JsonType),
+    /// Applies `inner` only if `condition`, applied to the same value,
+    /// focuses a single leaf equal to `expected`; otherwise `get` misses and
+    /// `set` and `delete` leave the document untouched.
+    Guard {
+        condition: Box<Lens>,
+        expected: Value,
+        inner: Box<Lens>,
+    },
+    /// Focuses every value stored under `key` in an object at most
+    /// `max_depth` levels below the focus, in document order, where the
+    /// focus's own entries are one level down. The search uses an explicit
+    /// stack, so untrusted documents can't exhaust the call stack, and
+    /// doesn't continue inside a matched value, which keeps the matches
+    /// disjoint for `get_mut`.
+    DescendLimited {
+        key: String,
+        max_depth: usize,
+    },
+    /// The whole document: `get` focuses it and `set` replaces it.
     #[default]
     Empty,
 }
 
+/// The function of a [`Lens::Map`]. Two `MapFn`s are equal only if they
+/// share the same function.
+#[derive(Clone)]
+pub struct MapFn(Arc<dyn Fn(&Value) -> Value + Send + Sync>);
+
+impl MapFn {
+    pub fn new<F: Fn(&Value) -> Value + Send + Sync + 'static>(f: F) -> Self {
+        MapFn(Arc::new(f))
+    }
+
+    fn apply_view<'a>(&self, view: View<'_>) -> View<'a> {
+        match view {
+            View::Borrow(value) => View::Owned((self.0)(value)),
+            View::Owned(value) => View::Owned((self.0)(&value)),
+            View::BorrowVec(views) => View::BorrowVec(
+                views
+                    .into_iter()
+                    .map(|view| self.apply_view(view))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for MapFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MapFn(..)")
+    }
+}
+
+impl PartialEq for MapFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The shape of a focus, as reported by [`Lens::focus_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusKind {
+    /// A single string, number, bool or `null`.
+    Scalar,
+    Array,
+    Object,
+    /// Several leaves, e.g. under a `ForEach`.
+    Multi,
+}
+
+impl FocusKind {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Array(_) => FocusKind::Array,
+            Value::Object(_) => FocusKind::Object,
+            _ => FocusKind::Scalar,
+        }
+    }
+}
+
+/// Where [`Lens::set_at`] places a key that isn't present yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    First,
+    Last,
+    /// Before the first key that sorts after the new one.
+    Sorted,
+}
+
+/// What [`Lens::set_with_policy`] does when the final `Index` is past the end
+/// of the array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexPolicy {
+    /// Leave the array as it is, like `set`.
+    Ignore,
+    /// Push the target onto the end of the array.
+    Append,
+    /// Pad the array with `null`s up to the index, then write the target.
+    /// An index too large to allocate for is ignored.
+    Fill,
+}
+
 impl Lens {
+    /// Sentinel `Index` selecting the last element of an array.
+    pub const LAST: usize = usize::MAX;
+
     pub fn get_mut<'a>(&'a self, value: &'a mut Value) -> Option<Modify<'a>> {
+        self.try_get_mut(value).ok()
+    }
+
+    pub fn try_get_mut<'a>(&'a self, value: &'a mut Value) -> Result<Modify<'a>, LensError> {
         match self {
-            Lens::Field(field) => value
-                .as_object_mut()
-                .and_then(|obj| obj.get_mut(field))
-                .map(Modify::BorrowMut),
-            Lens::Index(index) => value
-                .as_array_mut()
-                .and_then(|arr| arr.get_mut(*index))
-                .map(Modify::BorrowMut),
-            Lens::Compose(first, second) => {
-                if let Some(inner) = first.get_mut(value) {
-                    inner.get_mut(second.as_ref())
-                } else {
-                    None
+            Lens::Field(field) => match value {
+                Value::Object(obj) => obj
+                    .get_mut(field)
+                    .map(Modify::BorrowMut)
+                    .ok_or_else(|| self.error(LensErrorKind::MissingField(field.clone()))),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::FieldCI(field) => match value {
+                Value::Object(obj) => match find_ci(obj, field).cloned() {
+                    Some(key) => Ok(Modify::BorrowMut(&mut obj[&key])),
+                    None => Err(self.error(LensErrorKind::MissingField(field.clone()))),
+                },
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::Index(index) => match value {
+                Value::Array(arr) => {
+                    let len = arr.len();
+                    arr.get_mut(array_index(*index, len))
+                        .map(Modify::BorrowMut)
+                        .ok_or_else(|| {
+                            self.error(LensErrorKind::IndexOutOfBounds { index: *index, len })
+                        })
+                }
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Compose(first, second) => first
+                .try_get_mut(value)?
+                .try_get_mut(second)
+                .map_err(|err| err.within(first)),
+            Lens::ForEach => match value {
+                Value::Array(arr) => Ok(Modify::BorrowVec(
+                    arr.iter_mut().map(Modify::BorrowMut).collect(),
+                )),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Wildcard => match value {
+                Value::Object(obj) => Ok(Modify::BorrowVec(
+                    obj.values_mut().map(Modify::BorrowMut).collect(),
+                )),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::Glob(pattern) => match value {
+                Value::Object(obj) => Ok(Modify::BorrowVec(
+                    obj.iter_mut()
+                        .filter(|(key, _)| glob_match(pattern, key))
+                        .map(|(_, value)| Modify::BorrowMut(value))
+                        .collect(),
+                )),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::Take(n) => match value {
+                Value::Array(arr) => Ok(Modify::BorrowVec(
+                    arr.iter_mut().take(*n).map(Modify::BorrowMut).collect(),
+                )),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Skip(n) => match value {
+                Value::Array(arr) => Ok(Modify::BorrowVec(
+                    arr.iter_mut().skip(*n).map(Modify::BorrowMut).collect(),
+                )),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Coalesce(lenses) => {
+                let chosen = choose(lenses, value, true).or_else(|| choose(lenses, value, false));
+                match chosen.or(lenses.first()) {
+                    Some(lens) => lens.try_get_mut(value),
+                    None => Ok(Modify::BorrowVec(Vec::new())),
                 }
             }
-            Lens::ForEach => value
-                .as_array_mut()
-                .map(|arr| Modify::BorrowVec(arr.iter_mut().map(Modify::BorrowMut).collect())),
-            Lens::Empty => Some(Modify::BorrowMut(value)),
+            Lens::Union(lenses) => union_mut(lenses, value).ok_or_else(|| {
+                self.error(LensErrorKind::Unsupported(
+                    "mutable borrow of a union of anything but sibling fields or indices",
+                ))
+            }),
+            Lens::Embedded(_) => Err(self.error(LensErrorKind::Unsupported(
+                "mutable borrow of an embedded document",
+            ))),
+            #[cfg(feature = "base64")]
+            Lens::Base64(_) => Err(self.error(LensErrorKind::Unsupported(
+                "mutable borrow of an embedded document",
+            ))),
+            Lens::Cast(..) => Err(self.error(LensErrorKind::Unsupported(
+                "mutable borrow of a coerced value",
+            ))),
+            Lens::ById { field, value: id } => match value {
+                Value::Array(arr) => match position_by_id(arr, field, id) {
+                    Some(index) => Ok(Modify::BorrowMut(&mut arr[index])),
+                    None => Err(self.error(LensErrorKind::NoMatch)),
+                },
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::ByIds { field, values } => match value {
+                Value::Array(arr) => {
+                    let positions = positions_by_ids(arr, field, values);
+                    let mut slots: Vec<_> = arr.iter_mut().map(Some).collect();
+                    Ok(Modify::BorrowVec(
+                        positions
+                            .into_iter()
+                            .filter_map(|index| slots[index].take())
+                            .map(Modify::BorrowMut)
+                            .collect(),
+                    ))
+                }
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Map(..) => Err(self.error(LensErrorKind::Unsupported(
+                "mutable borrow of a mapped value",
+            ))),
+            Lens::Length => {
+                Err(self.error(LensErrorKind::Unsupported("mutable borrow of a length")))
+            }
+            Lens::Guard { inner, .. } => {
+                self.check_guard(value)?;
+                inner.try_get_mut(value)
+            }
+            Lens::DescendLimited { key, max_depth } => Ok(Modify::BorrowVec(
+                descend_mut(value, key, *max_depth)
+                    .into_iter()
+                    .map(Modify::BorrowMut)
+                    .collect(),
+            )),
+            Lens::Empty => Ok(Modify::BorrowMut(value)),
         }
     }
 
+    /// Mutable references to every leaf of the focus, empty if the lens
+    /// doesn't resolve.
+    pub fn get_all_mut<'a>(&'a self, value: &'a mut Value) -> Vec<&'a mut Value> {
+        self.get_mut(value)
+            .map(Modify::into_vec)
+            .unwrap_or_default()
+    }
+
     pub fn get<'a>(&'a self, value: &'a Value) -> Option<View<'a>> {
+        match self.get_chain(value) {
+            Some(value) => value.map(View::Borrow),
+            None => self.try_get(value).ok(),
+        }
+    }
+
+    /// Like `get`, but clones the focus so the result doesn't borrow from
+    /// `value`.
+    pub fn get_owned_view(&self, value: &Value) -> Option<OwnedView> {
+        self.get(value).map(OwnedView::from)
+    }
+
+    /// Clones the leaves of the focus into a single array, so the window
+    /// picked by a `Take` or `Skip` can be handled as one value. Unlike
+    /// [`View::into_value`], nested multi-focus views are flattened, and a
+    /// single focus is wrapped in a one-element array.
+    pub fn get_window_owned(&self, value: &Value) -> Option<Value> {
+        self.get(value)
+            .map(|view| Value::Array(view.into_iter().collect()))
+    }
+
+    /// Serializes a single focus into a `RawValue`, which can be embedded in
+    /// another document without going through a `Value` again. A
+    /// multi-focus yields `None`, like a focus that doesn't resolve.
+    #[cfg(feature = "raw_value")]
+    pub fn get_raw(&self, value: &Value) -> Option<Box<serde_json::value::RawValue>> {
+        match self.get(value)? {
+            View::Borrow(value) => serde_json::value::to_raw_value(value).ok(),
+            View::Owned(value) => serde_json::value::to_raw_value(&value).ok(),
+            View::BorrowVec(_) => None,
+        }
+    }
+
+    /// Writes the focus to `writer` as JSON, streaming the leaves of a
+    /// multi-focus as an array instead of collecting them into a `Value`
+    /// first. A focus that doesn't resolve is an `InvalidInput` error and
+    /// leaves the writer untouched.
+    pub fn serialize_focus<W: io::Write>(&self, value: &Value, writer: W) -> io::Result<()> {
+        let view = self
+            .try_get(value)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        serde_json::to_writer(writer, &view).map_err(io::Error::from)
+    }
+
+    /// Like `get`, but gives up with `CapExceeded` as soon as more than `max`
+    /// leaves are found, before collecting any of them. A lens that doesn't
+    /// resolve yields an empty multi-focus. Lenses that compute their values
+    /// (`Embedded`, `Cast`, `Map`, ...) can only be counted once the view is
+    /// built, so for them the cap bounds the result but not the work.
+    pub fn get_capped<'a>(&'a self, value: &'a Value, max: usize) -> Result<View<'a>, CapExceeded> {
+        if !self.borrows() {
+            let view = self.get(value).unwrap_or(View::BorrowVec(Vec::new()));
+            return if view.leaf_count() > max {
+                Err(CapExceeded { max })
+            } else {
+                Ok(view)
+            };
+        }
+        let mut count = 0;
+        let flow = self.traverse(value, &mut Vec::new(), &mut |_, _| {
+            count += 1;
+            if count > max {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        if flow.is_break() {
+            return Err(CapExceeded { max });
+        }
+        Ok(self.get(value).unwrap_or(View::BorrowVec(Vec::new())))
+    }
+
+    /// Whether the focus is a single scalar, array or object, or a
+    /// multi-focus. `None` if the lens doesn't resolve.
+    pub fn focus_kind(&self, value: &Value) -> Option<FocusKind> {
+        let kind = match self.get(value)? {
+            View::BorrowVec(_) => FocusKind::Multi,
+            View::Borrow(value) => FocusKind::of(value),
+            View::Owned(value) => FocusKind::of(&value),
+        };
+        Some(kind)
+    }
+
+    /// Like `get`, but treats `null` as absent: a single `null` focus yields
+    /// `None` and `null` leaves of a multi-focus are left out.
+    pub fn get_non_null<'a>(&'a self, value: &'a Value) -> Option<View<'a>> {
+        self.get(value)?.non_null()
+    }
+
+    /// The single focus as an owned `String`, `None` if it isn't a string.
+    pub fn get_string(&self, value: &Value) -> Option<String> {
+        match self.get(value)? {
+            View::Borrow(Value::String(text)) => Some(text.clone()),
+            View::Owned(Value::String(text)) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Deserializes the single focus into `T`, reading a borrowed focus in
+    /// place rather than cloning it. `None` for a multi-focus, or if the
+    /// focus doesn't have the shape `T` expects.
+    pub fn get_as<T: DeserializeOwned>(&self, value: &Value) -> Option<T> {
+        match self.get(value)? {
+            View::Borrow(value) => T::deserialize(value).ok(),
+            View::Owned(value) => serde_json::from_value(value).ok(),
+            View::BorrowVec(_) => None,
+        }
+    }
+
+    /// Like [`Lens::get_as`], but a `null` focus is `Some(None)`, telling a
+    /// path that resolves to `null` apart from one that doesn't resolve. A
+    /// focus that is neither `null` nor a `T` is still `None`.
+    pub fn get_opt_as<T: DeserializeOwned>(&self, value: &Value) -> Option<Option<T>> {
+        self.get_as::<Option<T>>(value)
+    }
+
+    /// The single focus as a borrowed object, `None` if it's anything else or
+    /// was computed by the lens rather than borrowed from `value`.
+    pub fn get_object<'a>(&'a self, value: &'a Value) -> Option<&'a Map<String, Value>> {
+        match self.get(value)? {
+            View::Borrow(Value::Object(obj)) => Some(obj),
+            _ => None,
+        }
+    }
+
+    /// Like [`Lens::get_object`], for an array focus.
+    pub fn get_array<'a>(&'a self, value: &'a Value) -> Option<&'a Vec<Value>> {
+        match self.get(value)? {
+            View::Borrow(Value::Array(arr)) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Compares the arrays focused in `old` and `new` index by index: common
+    /// indices whose elements differ are `Changed`, and the tail of the
+    /// longer array is `Added` or `Removed`. Elements aren't matched up
+    /// across indices, so an insertion shows up as a run of changes. `None`
+    /// unless both focuses are single arrays.
+    pub fn array_diff(&self, old: &Value, new: &Value) -> Option<Vec<ArrayChange>> {
+        let (old, new) = (self.get_array(old)?, self.get_array(new)?);
+        let mut changes = Vec::new();
+        for (index, (old, new)) in old.iter().zip(new).enumerate() {
+            if old != new {
+                changes.push(ArrayChange::Changed(index, old.clone(), new.clone()));
+            }
+        }
+        let common = old.len().min(new.len());
+        changes.extend(
+            old.iter()
+                .enumerate()
+                .skip(common)
+                .map(|(index, value)| ArrayChange::Removed(index, value.clone())),
+        );
+        changes.extend(
+            new.iter()
+                .enumerate()
+                .skip(common)
+                .map(|(index, value)| ArrayChange::Added(index, value.clone())),
+        );
+        Some(changes)
+    }
+
+    /// The single focus parsed as an RFC 3339 timestamp and converted to
+    /// UTC. `None` if it isn't a string or doesn't parse.
+    #[cfg(feature = "chrono")]
+    pub fn get_datetime(&self, value: &Value) -> Option<chrono::DateTime<chrono::Utc>> {
+        let text = self.get_string(value)?;
+        let datetime = chrono::DateTime::parse_from_rfc3339(&text).ok()?;
+        Some(datetime.with_timezone(&chrono::Utc))
+    }
+
+    /// The single focus together with the name of its JSON type: `"object"`,
+    /// `"array"`, `"string"`, `"number"`, `"bool"` or `"null"`. `None` for a
+    /// multi-focus, and for values computed by the lens rather than borrowed
+    /// from `value`.
+    pub fn get_typed<'a>(&'a self, value: &'a Value) -> Option<(&'static str, &'a Value)> {
+        match self.get(value)? {
+            View::Borrow(value) => Some((type_name(value), value)),
+            View::BorrowVec(_) | View::Owned(_) => None,
+        }
+    }
+
+    /// Whether the focuses of `a` and `b` hold equal values, comparing
+    /// multi-focus leaves in order. `false` if either side doesn't resolve.
+    pub fn eq_at(&self, a: &Value, b: &Value) -> bool {
+        match (self.get(a), self.get(b)) {
+            (Some(a), Some(b)) => a.same_values(&b),
+            _ => false,
+        }
+    }
+
+    /// Whether the lens resolves against `value`, even if only to `null` or
+    /// to an empty multi-focus.
+    pub fn exists(&self, value: &Value) -> bool {
+        self.get(value).is_some()
+    }
+
+    /// The first leaf the lens focuses in document order, found without
+    /// visiting the rest. Unlike the single-focus getters, a multi-focus
+    /// yields its first leaf rather than `None`. Values computed by the lens
+    /// can't be borrowed and are skipped.
+    pub fn first<'a>(&'a self, value: &'a Value) -> Option<&'a Value> {
+        let mut first = None;
+        let _ = self.traverse(value, &mut Vec::new(), &mut |_, leaf| {
+            first = Some(leaf);
+            ControlFlow::Break(())
+        });
+        first
+    }
+
+    /// Whether any focused leaf equals `needle`. Stops at the first match
+    /// rather than collecting the focus, unless the lens computes its values
+    /// (`Embedded`, `Cast`, ...).
+    pub fn contains(&self, value: &Value, needle: &Value) -> bool {
+        if !self.borrows() {
+            return self
+                .get(value)
+                .is_some_and(|view| view.into_iter().any(|leaf| leaf == *needle));
+        }
+        self.traverse(value, &mut Vec::new(), &mut |_, leaf| {
+            if leaf == needle {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .is_break()
+    }
+
+    /// Whether every one of `lenses` resolves against `value`.
+    pub fn all_exist(lenses: &[Lens], value: &Value) -> bool {
+        lenses.iter().all(|lens| lens.exists(value))
+    }
+
+    /// Whether at least one of `lenses` resolves against `value`.
+    pub fn any_exist(lenses: &[Lens], value: &Value) -> bool {
+        lenses.iter().any(|lens| lens.exists(value))
+    }
+
+    /// Clears `buf` and fills it with every leaf the lens focuses, flattened
+    /// in document order, reusing its allocation across calls. Lenses that
+    /// yield owned values (`Embedded`, `Cast`, ...) can't borrow into `buf`
+    /// and leave it empty. Returns whether any leaf was found.
+    pub fn get_into<'a>(&'a self, value: &'a Value, buf: &mut Vec<&'a Value>) -> bool {
+        buf.clear();
+        if self.borrows() {
+            self.leaves(value, &mut |value| buf.push(value));
+        }
+        !buf.is_empty()
+    }
+
+    /// Resolves pure `Field`/`FieldCI`/`Index` chains directly, without
+    /// building the intermediate views or the errors of `try_get`. Returns
+    /// `None` if the lens contains any other segment.
+    fn get_chain<'a>(&self, value: &'a Value) -> Option<Option<&'a Value>> {
         match self {
-            Lens::Field(field) => value
-                .as_object()
-                .and_then(|obj| obj.get(field))
-                .map(View::Borrow),
-            Lens::Index(index) => value
-                .as_array()
-                .and_then(|arr| arr.get(*index))
-                .map(View::Borrow),
-            Lens::Compose(first, second) => {
-                first.get(value).and_then(|view| view.get(second.as_ref()))
+            Lens::Field(_) | Lens::FieldCI(_) | Lens::Index(_) => Some(step(self, value)),
+            Lens::Compose(first, second) => match first.get_chain(value)? {
+                Some(value) => second.get_chain(value),
+                None => Some(None),
+            },
+            Lens::Empty => Some(Some(value)),
+            _ => None,
+        }
+    }
+
+    pub fn try_get<'a>(&'a self, value: &'a Value) -> Result<View<'a>, LensError> {
+        match self {
+            Lens::Field(field) => match value {
+                Value::Object(obj) => obj
+                    .get(field)
+                    .map(View::Borrow)
+                    .ok_or_else(|| self.error(LensErrorKind::MissingField(field.clone()))),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::FieldCI(field) => match value {
+                Value::Object(obj) => find_ci(obj, field)
+                    .map(|key| View::Borrow(&obj[key]))
+                    .ok_or_else(|| self.error(LensErrorKind::MissingField(field.clone()))),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::Index(index) => match value {
+                Value::Array(arr) => arr
+                    .get(array_index(*index, arr.len()))
+                    .map(View::Borrow)
+                    .ok_or_else(|| {
+                        self.error(LensErrorKind::IndexOutOfBounds {
+                            index: *index,
+                            len: arr.len(),
+                        })
+                    }),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Compose(first, second) => first
+                .try_get(value)?
+                .try_get(second)
+                .map_err(|err| err.within(first)),
+            Lens::ForEach => match value {
+                Value::Array(arr) => Ok(View::BorrowVec(arr.iter().map(View::Borrow).collect())),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Wildcard => match value {
+                Value::Object(obj) => Ok(View::BorrowVec(obj.values().map(View::Borrow).collect())),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::Glob(pattern) => match value {
+                Value::Object(obj) => Ok(View::BorrowVec(
+                    obj.iter()
+                        .filter(|(key, _)| glob_match(pattern, key))
+                        .map(|(_, value)| View::Borrow(value))
+                        .collect(),
+                )),
+                value => Err(self.mismatch("object", value)),
+            },
+            Lens::Take(n) => match value {
+                Value::Array(arr) => Ok(View::BorrowVec(
+                    arr.iter().take(*n).map(View::Borrow).collect(),
+                )),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Skip(n) => match value {
+                Value::Array(arr) => Ok(View::BorrowVec(
+                    arr.iter().skip(*n).map(View::Borrow).collect(),
+                )),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Coalesce(lenses) => {
+                let chosen = choose(lenses, value, true).or_else(|| choose(lenses, value, false));
+                match chosen.or(lenses.first()) {
+                    Some(lens) => lens.try_get(value),
+                    None => Ok(View::BorrowVec(Vec::new())),
+                }
             }
-            Lens::ForEach => value
-                .as_array()
-                .map(|arr| View::BorrowVec(arr.iter().map(View::Borrow).collect())),
-            Lens::Empty => Some(View::Borrow(value)),
+            Lens::Union(lenses) => {
+                let mut views = Vec::new();
+                for view in lenses.iter().filter_map(|lens| lens.get(value)) {
+                    splice(&mut views, view);
+                }
+                Ok(View::BorrowVec(views))
+            }
+            Lens::Embedded(inner) => inner
+                .try_get(&decode_json(value).map_err(|kind| self.error(kind))?)
+                .map(View::into_owned)
+                .map_err(|err| err.inside("embedded")),
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => inner
+                .try_get(&decode_base64(value).map_err(|kind| self.error(kind))?)
+                .map(View::into_owned)
+                .map_err(|err| err.inside("base64")),
+            Lens::Cast(inner, ty) => ty
+                .cast_view(inner.try_get(value)?)
+                .map_err(|kind| self.error(kind)),
+            Lens::ById { field, value: id } => match value {
+                Value::Array(arr) => match position_by_id(arr, field, id) {
+                    Some(index) => Ok(View::Borrow(&arr[index])),
+                    None => Err(self.error(LensErrorKind::NoMatch)),
+                },
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::ByIds { field, values } => match value {
+                Value::Array(arr) => Ok(View::BorrowVec(
+                    positions_by_ids(arr, field, values)
+                        .into_iter()
+                        .map(|index| View::Borrow(&arr[index]))
+                        .collect(),
+                )),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Map(inner, f) => Ok(f.apply_view(inner.try_get(value)?)),
+            Lens::Length => match value {
+                Value::Array(arr) => Ok(View::Owned(Value::from(arr.len()))),
+                value => Err(self.mismatch("array", value)),
+            },
+            Lens::Guard { inner, .. } => {
+                self.check_guard(value)?;
+                inner.try_get(value)
+            }
+            Lens::DescendLimited { key, max_depth } => Ok(View::BorrowVec(
+                descend(value, key, *max_depth)
+                    .into_iter()
+                    .map(|(_, value)| View::Borrow(value))
+                    .collect(),
+            )),
+            Lens::Empty => Ok(View::Borrow(value)),
         }
     }
 
+    /// Writes `target` at the focus. Setting through [`Lens::Empty`]
+    /// replaces the whole document.
     pub fn set(&self, source: &mut Value, target: Value) {
-        dbg!(self);
+        let _ = self.try_set(source, target);
+    }
+
+    pub fn try_set(&self, source: &mut Value, target: Value) -> Result<(), LensError> {
+        self.write(source, target, &mut None)
+    }
+
+    /// The traversal behind `try_set`. With `changed` set, a leaf that
+    /// already equals `target` is left alone and the flag records whether
+    /// any leaf was written.
+    pub(crate) fn write(
+        &self,
+        source: &mut Value,
+        target: Value,
+        changed: &mut Option<&mut bool>,
+    ) -> Result<(), LensError> {
         match self {
-            Lens::Field(field) => {
-                if let Some(obj) = source.as_object_mut() {
-                    obj.insert(field.clone(), target);
+            Lens::Field(field) => match source {
+                Value::Object(obj) => {
+                    insert(obj, field, target, changed);
+                    Ok(())
                 }
-            }
-            Lens::Index(index) => {
-                if let Some(arr) = source.as_array_mut() {
-                    if *index < arr.len() {
-                        arr[*index] = target;
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::FieldCI(field) => match source {
+                Value::Object(obj) => {
+                    let key = find_ci(obj, field).unwrap_or(field).clone();
+                    insert(obj, &key, target, changed);
+                    Ok(())
+                }
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::Index(index) => match source {
+                Value::Array(arr) => {
+                    let len = arr.len();
+                    match arr.get_mut(array_index(*index, len)) {
+                        Some(slot) => {
+                            assign(slot, target, changed);
+                            Ok(())
+                        }
+                        None => {
+                            Err(self.error(LensErrorKind::IndexOutOfBounds { index: *index, len }))
+                        }
                     }
                 }
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Compose(first, second) => first
+                .try_get_mut(source)?
+                .write(second, target, changed)
+                .map_err(|err| err.within(first)),
+            Lens::ForEach => match source {
+                Value::Array(arr) => {
+                    arr.iter_mut().for_each(|source| {
+                        assign(source, target.clone(), changed);
+                    });
+                    Ok(())
+                }
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Wildcard => match source {
+                Value::Object(obj) => {
+                    obj.values_mut().for_each(|source| {
+                        assign(source, target.clone(), changed);
+                    });
+                    Ok(())
+                }
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::Glob(pattern) => match source {
+                Value::Object(obj) => {
+                    obj.iter_mut()
+                        .filter(|(key, _)| glob_match(pattern, key))
+                        .for_each(|(_, source)| {
+                            assign(source, target.clone(), changed);
+                        });
+                    Ok(())
+                }
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::Take(_) | Lens::Skip(_) | Lens::ByIds { .. } => {
+                self.try_get_mut(source)?.for_each(&mut |source| {
+                    assign(source, target.clone(), changed);
+                });
+                Ok(())
             }
-            Lens::Compose(first, second) => {
-                if let Some(modify) = first.get_mut(source) {
-                    modify.set(second, target);
+            Lens::Coalesce(lenses) => match choose(lenses, source, false).or(lenses.first()) {
+                Some(lens) => lens.write(source, target, changed),
+                None => Ok(()),
+            },
+            Lens::Union(lenses) => {
+                for lens in lenses {
+                    let _ = lens.write(source, target.clone(), changed);
                 }
+                Ok(())
             }
-            Lens::ForEach => {
-                if let Some(arr) = source.as_array_mut() {
-                    arr.iter_mut().for_each(|source| {
-                        *source = target.clone();
-                    });
+            Lens::Embedded(inner) => {
+                let mut document = decode_json(source).map_err(|kind| self.error(kind))?;
+                if write_inside(inner, &mut document, target, changed)
+                    .map_err(|err| err.inside("embedded"))?
+                {
+                    *source = encode_json(&document);
+                }
+                Ok(())
+            }
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => {
+                let mut document = decode_base64(source).map_err(|kind| self.error(kind))?;
+                if write_inside(inner, &mut document, target, changed)
+                    .map_err(|err| err.inside("base64"))?
+                {
+                    *source = encode_base64(&document);
+                }
+                Ok(())
+            }
+            Lens::Cast(inner, ty) => {
+                let target = ty.coerce(&target).map_err(|kind| self.error(kind))?;
+                inner.write(source, target, changed)
+            }
+            Lens::ById { field, value } => match source {
+                Value::Array(arr) => match position_by_id(arr, field, value) {
+                    Some(index) => {
+                        assign(&mut arr[index], target, changed);
+                        Ok(())
+                    }
+                    None => Err(self.error(LensErrorKind::NoMatch)),
+                },
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Map(inner, _) => inner.write(source, target, changed),
+            Lens::Length => match (source, target.as_u64()) {
+                (Value::Array(arr), Some(len)) => {
+                    let too_long = || self.error(LensErrorKind::Unsupported("length too large"));
+                    let len = usize::try_from(len).map_err(|_| too_long())?;
+                    arr.try_reserve(len.saturating_sub(arr.len()))
+                        .map_err(|_| too_long())?;
+                    if arr.len() != len {
+                        mark(changed);
+                    }
+                    arr.resize(len, Value::Null);
+                    Ok(())
+                }
+                (Value::Array(_), None) => Err(self.error(LensErrorKind::Unsupported(
+                    "length that isn't a non-negative integer",
+                ))),
+                (source, _) => Err(self.mismatch("array", source)),
+            },
+            Lens::Guard { inner, .. } => {
+                self.check_guard(source)?;
+                inner.write(source, target, changed)
+            }
+            Lens::DescendLimited { key, max_depth } => {
+                for value in descend_mut(source, key, *max_depth) {
+                    assign(value, target.clone(), changed);
                 }
+                Ok(())
+            }
+            Lens::Empty => {
+                assign(source, target, changed);
+                Ok(())
             }
-            Lens::Empty => {}
         }
     }
 
-    pub fn select<I: Select>(self, item: I) -> Self {
-        item.pipe(self)
+    /// Like `set`, and returns the whole updated document pretty-printed
+    /// with two-space indentation, as `serde_json::to_string_pretty` would.
+    pub fn set_and_format(&self, source: &mut Value, target: Value) -> String {
+        self.set(source, target);
+        format!("{:#}", source)
     }
 
-    pub fn new<I: Select>(item: I) -> Self {
-        item.pipe(Lens::Empty)
+    /// Like `set`, but leaves are only written where they differ from
+    /// `target`, so an unchanged document isn't touched at all. Keys `set`
+    /// would insert are still inserted. Returns whether anything changed.
+    pub fn set_if_changed(&self, source: &mut Value, target: Value) -> bool {
+        let mut changed = false;
+        let _ = self.write(source, target, &mut Some(&mut changed));
+        changed
     }
 
-    pub fn foreach() -> Self {
-        Lens::ForEach
+    /// Like `set`, but returns a `Change` for every leaf whose value the
+    /// write changed, one per element of a multi-focus. Leaves inside
+    /// `Embedded` documents have no path and aren't reported.
+    pub fn set_observed(&self, source: &mut Value, target: Value) -> Vec<Change> {
+        let mut before = HashMap::new();
+        self.walk(source, |path, value| {
+            before.insert(path.to_vec(), value.clone());
+        });
+        self.set(source, target);
+
+        let mut changes = Vec::new();
+        self.walk(source, |path, value| {
+            let old = before.remove(path);
+            if old.as_ref() != Some(value) {
+                changes.push(Change {
+                    path: Lens::from_segments(path).to_string(),
+                    old,
+                    new: value.clone(),
+                });
+            }
+        });
+        changes
     }
 
-    pub fn each(self) -> Self {
-        Lens::ForEach.pipe(self)
+    /// Like `set`, but calls `on_miss` with the path up to the segment that
+    /// couldn't be descended when the write can't land. As with `get`,
+    /// elements of a multi-focus that miss are skipped rather than reported.
+    pub fn set_with<F: FnMut(&str)>(&self, source: &mut Value, target: Value, mut on_miss: F) {
+        if let Err(err) = self.try_set(source, target) {
+            on_miss(&err.path);
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use serde_json::json;
+    /// Serializes `value` and writes it like `set`, so every element of a
+    /// `ForEach` gets its own copy. Nothing is written if `value` can't be
+    /// represented as JSON, such as a map with non-string keys.
+    pub fn set_from<T: Serialize>(&self, source: &mut Value, value: &T) {
+        if let Ok(target) = serde_json::to_value(value) {
+            self.set(source, target);
+        }
+    }
 
-    #[test]
-    fn test_field() {
-        let mut value = json!({"a": 1});
-        let lens = Lens::new("a");
-        let view = lens.get(&value).unwrap();
-        assert_eq!(view, View::Borrow(&json!(1)));
+    /// Writes `template` as a string at the focus, with every `${VAR}`
+    /// replaced by its entry in `vars`. Placeholders without an entry, and
+    /// a `${` that is never closed, are left in the string as written, so
+    /// a template can be expanded in several passes.
+    pub fn set_interpolated(
+        &self,
+        source: &mut Value,
+        template: &str,
+        vars: &HashMap<String, String>,
+    ) {
+        self.set(source, Value::String(interpolate(template, vars)))
+    }
 
-        lens.set(&mut value, json!(2));
-        assert_eq!(value, json!({"a": 2}));
+    /// Like `set`, but when the last segment is an `Index` past the end of
+    /// the array, `policy` decides whether the write is dropped, appended or
+    /// padded into place. `Lens::LAST` on an empty array appends under both
+    /// `Append` and `Fill`.
+    pub fn set_with_policy(&self, source: &mut Value, target: Value, policy: IndexPolicy) {
+        set_parts_with_policy(&self.parts(), source, target, policy)
     }
 
-    #[test]
-    fn test_index() {
-        let mut value = json!([1, 2, 3]);
-        let lens = Lens::new(1);
-        let view = lens.get(&value).unwrap();
-        assert_eq!(view, View::Borrow(&json!(2)));
+    /// Like `set`, but a `null` met where a `Field` or `FieldCI` is applied
+    /// is replaced with an empty object first, so `a.b` can be written while
+    /// `a` is `null`. Unlike [`Lens::ensure`], absent keys still miss.
+    pub fn set_through_null(&self, source: &mut Value, target: Value) {
+        set_parts_through_null(&self.parts(), source, target)
+    }
 
-        lens.set(&mut value, json!(4));
-        assert_eq!(value, json!([1, 4, 3]));
+    /// Writes `default` at the focus only if it's absent, creating missing
+    /// or `null` intermediates along the way: an object before a `Field`, an
+    /// array before an `Index`, padding arrays with `null` up to the index.
+    /// Existing values, including `null` leaves, are left intact and values
+    /// of the wrong type aren't replaced. A `ForEach` ensures the rest of the
+    /// path in every element. Returns whether anything was created; an index
+    /// too large to pad up to creates nothing.
+    pub fn ensure(&self, source: &mut Value, default: Value) -> bool {
+        ensure_parts(&self.parts(), source, default)
     }
 
-    #[test]
-    fn test_compose() {
-        let mut value = json!({"a": [1, 2, 3]});
-        let lens = Lens::new("a").select(1);
-        let view = lens.get(&value).unwrap();
-        assert_eq!(view, View::Borrow(&json!(2)));
+    /// Like `set`, but a `Field` missing from its object is inserted at
+    /// `position`; existing keys keep their place. Key order is only
+    /// observable with the `preserve_order` feature, without it objects are
+    /// always sorted and `position` has no effect.
+    pub fn set_at(&self, source: &mut Value, target: Value, position: InsertPosition) {
+        match self {
+            Lens::Field(field) => {
+                if let Some(obj) = source.as_object_mut() {
+                    insert_at(obj, field, target, position);
+                }
+            }
+            Lens::Compose(first, second) => {
+                if let Some(modify) = first.get_mut(source) {
+                    modify.for_each(&mut |value| second.set_at(value, target.clone(), position));
+                }
+            }
+            lens => lens.set(source, target),
+        }
+    }
 
-        lens.set(&mut value, json!(4));
-        assert_eq!(value, json!({"a": [1, 4, 3]}));
+    /// Renames the keys of every focused object according to `mapping`,
+    /// leaving unmapped keys alone. All matching keys are taken out before
+    /// any is reinserted, so swaps work, and a renamed key overwrites an
+    /// existing key of the same name.
+    pub fn rename_keys(&self, source: &mut Value, mapping: &HashMap<String, String>) {
+        for leaf in self.get_all_mut(source) {
+            let Some(obj) = leaf.as_object_mut() else {
+                continue;
+            };
+            let renamed: Vec<_> = obj
+                .keys()
+                .filter_map(|key| Some((key.clone(), mapping.get(key)?.clone())))
+                .collect();
+            let entries: Vec<_> = renamed
+                .into_iter()
+                .filter_map(|(key, name)| Some((name, obj.remove(&key)?)))
+                .collect();
+            obj.extend(entries);
+        }
     }
 
-    #[test]
-    fn test_for_each() {
-        let mut value = json!([{"a": 1}, {"a": 2}, {"a": 3}]);
+    /// Appends `item` to every focused array. Returns whether the focus held
+    /// any array.
+    pub fn push(&self, source: &mut Value, item: Value) -> bool {
+        let mut pushed = false;
+        for leaf in self.get_all_mut(source) {
+            if let Value::Array(arr) = leaf {
+                arr.push(item.clone());
+                pushed = true;
+            }
+        }
+        pushed
+    }
 
-        let lens = Lens::foreach().select("a");
-        let view = lens.get(&value).unwrap();
-        assert_eq!(
-            view,
-            View::BorrowVec(vec![
-                View::Borrow(&json!(1)),
-                View::Borrow(&json!(2)),
-                View::Borrow(&json!(3))
-            ])
-        );
+    /// Removes and returns the last element of a focused array, `None` if
+    /// it's empty or not an array. Popping from several arrays at once has
+    /// no single result, so a multi-focus yields `None` and is left as is.
+    pub fn pop(&self, source: &mut Value) -> Option<Value> {
+        match self.get_mut(source)? {
+            Modify::BorrowMut(Value::Array(arr)) => arr.pop(),
+            _ => None,
+        }
+    }
 
-        lens.set(&mut value, json!(4));
-        assert_eq!(value, json!([{"a": 4}, {"a": 4}, {"a": 4}]));
+    /// Hands the single focus to `f` for an in-place read-modify-write and
+    /// returns its result. A multi-focus or a missing focus yields `None`
+    /// without calling `f`.
+    pub fn modify<R, F: FnOnce(&mut Value) -> R>(&self, source: &mut Value, f: F) -> Option<R> {
+        match self.get_mut(source)? {
+            Modify::BorrowMut(value) => Some(f(value)),
+            Modify::BorrowVec(_) => None,
+        }
     }
-    #[test]
-    fn test_deeply_nested() {
-        let mut value = json!({
-            "a": {
-                "b": {
-                    "c": [1, 2, {"d": 3}]
-                }
+
+    /// Calls `f` on every focused leaf along with the index of the array
+    /// element it came from, as in [`Lens::get_indexed`]: the innermost
+    /// index for nested `ForEach`es. Leaves reached without passing through
+    /// an array are skipped, and so are values computed by the lens.
+    pub fn over_indexed<F: Fn(usize, &mut Value)>(&self, source: &mut Value, f: F) {
+        self.walk_mut(source, |path, leaf| {
+            if let Some(index) = last_index(path) {
+                f(index, leaf);
             }
         });
+    }
 
-        let lens = Lens::default()
-            .select("a")
-            .select("b")
-            .select("c")
-            .select(2)
-            .select("d");
-        let view = lens.get(&value).unwrap();
-        assert_eq!(view, View::Borrow(&json!(3)));
-
-        lens.set(&mut value, json!(4));
-        assert_eq!(
-            value,
-            json!({
-                "a": {
-                    "b": {
-                        "c": [1, 2, {"d": 4}]
-                    }
+    /// Swaps elements `i` and `j` of every focused array that has both.
+    /// Returns whether any array was swapped.
+    pub fn swap(&self, source: &mut Value, i: usize, j: usize) -> bool {
+        let mut swapped = false;
+        for leaf in self.get_all_mut(source) {
+            if let Value::Array(arr) = leaf {
+                if i < arr.len() && j < arr.len() {
+                    arr.swap(i, j);
+                    swapped = true;
                 }
-            })
-        );
+            }
+        }
+        swapped
+    }
+
+    /// Adds `by` to every focused number, keeping integers integral and
+    /// exact when `by` is a whole number and the sum fits in an `i64` or a
+    /// `u64`. Other values are
+    /// skipped. Returns whether any number was updated.
+    pub fn increment(&self, source: &mut Value, by: f64) -> bool {
+        let mut updated = false;
+        for leaf in self.get_all_mut(source) {
+            let Value::Number(number) = leaf else {
+                continue;
+            };
+            if let Some(sum) = add_number(number, by) {
+                *number = sum;
+                updated = true;
+            }
+        }
+        updated
+    }
+
+    /// Returns a copy of `value` in which every focused object has the keys
+    /// of a `#[serde(flatten)]`ed struct grouped back under their parent.
+    /// Each rule maps a parent key to the child keys moved under it; the
+    /// parent is only created if a child is present, and an existing parent
+    /// object keeps its own keys. A rule whose parent key already holds
+    /// something other than an object is skipped, leaving its children in
+    /// place.
+    pub fn unflatten(&self, value: &Value, rules: &[(String, Vec<String>)]) -> Value {
+        let mut value = value.clone();
+        for leaf in self.get_all_mut(&mut value) {
+            let Some(obj) = leaf.as_object_mut() else {
+                continue;
+            };
+            for (parent, children) in rules {
+                if obj.get(parent).is_some_and(|parent| !parent.is_object()) {
+                    continue;
+                }
+                let moved: Map<_, _> = children
+                    .iter()
+                    .filter_map(|child| Some((child.clone(), obj.remove(child)?)))
+                    .collect();
+                if moved.is_empty() {
+                    continue;
+                }
+                let parent = obj
+                    .entry(parent.clone())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if let Some(parent) = parent.as_object_mut() {
+                    parent.extend(moved);
+                }
+            }
+        }
+        value
+    }
+
+    /// Truncates every focused string to at most `max_chars` characters,
+    /// counting chars rather than bytes. Other values are left untouched.
+    pub fn truncate_str(&self, source: &mut Value, max_chars: usize) {
+        for leaf in self.get_all_mut(source) {
+            if let Value::String(text) = leaf {
+                if let Some((end, _)) = text.char_indices().nth(max_chars) {
+                    text.truncate(end);
+                }
+            }
+        }
+    }
+
+    /// Replaces every focused string with `f` applied to it. Other values
+    /// are left untouched.
+    pub fn map_strings<F: Fn(&str) -> String>(&self, source: &mut Value, f: F) {
+        for leaf in self.get_all_mut(source) {
+            if let Value::String(text) = leaf {
+                *text = f(text);
+            }
+        }
+    }
+
+    /// Removes the focus from its parent. Deleting through [`Lens::Empty`]
+    /// replaces the whole document with `null`.
+    pub fn delete(&self, source: &mut Value) {
+        let _ = self.try_delete(source);
+    }
+
+    pub fn try_delete(&self, source: &mut Value) -> Result<(), LensError> {
+        match self {
+            Lens::Field(field) => match source {
+                Value::Object(obj) => obj
+                    .remove(field)
+                    .map(drop)
+                    .ok_or_else(|| self.error(LensErrorKind::MissingField(field.clone()))),
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::FieldCI(field) => match source {
+                Value::Object(obj) => match find_ci(obj, field).cloned() {
+                    Some(key) => {
+                        obj.remove(&key);
+                        Ok(())
+                    }
+                    None => Err(self.error(LensErrorKind::MissingField(field.clone()))),
+                },
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::Index(index) => match source {
+                Value::Array(arr) if array_index(*index, arr.len()) < arr.len() => {
+                    arr.remove(array_index(*index, arr.len()));
+                    Ok(())
+                }
+                Value::Array(arr) => Err(self.error(LensErrorKind::IndexOutOfBounds {
+                    index: *index,
+                    len: arr.len(),
+                })),
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Compose(first, second) => first
+                .try_get_mut(source)?
+                .try_delete(second)
+                .map_err(|err| err.within(first)),
+            Lens::ForEach => match source {
+                Value::Array(arr) => {
+                    arr.clear();
+                    Ok(())
+                }
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Wildcard => match source {
+                Value::Object(obj) => {
+                    obj.clear();
+                    Ok(())
+                }
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::Glob(pattern) => match source {
+                Value::Object(obj) => {
+                    obj.retain(|key, _| !glob_match(pattern, key));
+                    Ok(())
+                }
+                source => Err(self.mismatch("object", source)),
+            },
+            Lens::Take(n) => match source {
+                Value::Array(arr) => {
+                    arr.drain(..(*n).min(arr.len()));
+                    Ok(())
+                }
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Skip(n) => match source {
+                Value::Array(arr) => {
+                    arr.truncate(*n);
+                    Ok(())
+                }
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Coalesce(lenses) => match choose(lenses, source, false).or(lenses.first()) {
+                Some(lens) => lens.try_delete(source),
+                None => Ok(()),
+            },
+            Lens::Union(lenses) => {
+                for lens in lenses.iter().rev() {
+                    lens.delete(source);
+                }
+                Ok(())
+            }
+            Lens::Embedded(inner) => {
+                let mut document = decode_json(source).map_err(|kind| self.error(kind))?;
+                inner
+                    .try_delete(&mut document)
+                    .map_err(|err| err.inside("embedded"))?;
+                *source = encode_json(&document);
+                Ok(())
+            }
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => {
+                let mut document = decode_base64(source).map_err(|kind| self.error(kind))?;
+                inner
+                    .try_delete(&mut document)
+                    .map_err(|err| err.inside("base64"))?;
+                *source = encode_base64(&document);
+                Ok(())
+            }
+            Lens::ById { field, value } => match source {
+                Value::Array(arr) => match position_by_id(arr, field, value) {
+                    Some(index) => {
+                        arr.remove(index);
+                        Ok(())
+                    }
+                    None => Err(self.error(LensErrorKind::NoMatch)),
+                },
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::ByIds { field, values } => match source {
+                Value::Array(arr) => {
+                    let mut positions = positions_by_ids(arr, field, values);
+                    positions.sort_unstable();
+                    for index in positions.into_iter().rev() {
+                        arr.remove(index);
+                    }
+                    Ok(())
+                }
+                source => Err(self.mismatch("array", source)),
+            },
+            Lens::Cast(inner, _) | Lens::Map(inner, _) => inner.try_delete(source),
+            Lens::Length => Err(self.error(LensErrorKind::Unsupported("deleting a length"))),
+            Lens::Guard { inner, .. } => {
+                self.check_guard(source)?;
+                inner.try_delete(source)
+            }
+            Lens::DescendLimited { key, max_depth } => {
+                let paths: Vec<_> = descend(source, key, *max_depth)
+                    .into_iter()
+                    .map(|(path, _)| path)
+                    .collect();
+                // Matches are never nested and only remove object keys, so
+                // deleting one doesn't move the others.
+                for path in paths {
+                    Lens::from_segments(&path).delete(source);
+                }
+                Ok(())
+            }
+            Lens::Empty => {
+                *source = Value::Null;
+                Ok(())
+            }
+        }
+    }
+
+    /// Deep-merges `patch` into every focus using JSON Merge Patch semantics.
+    pub fn merge(&self, source: &mut Value, patch: Value) {
+        let _ = self.try_merge(source, patch);
+    }
+
+    pub fn try_merge(&self, source: &mut Value, patch: Value) -> Result<(), LensError> {
+        self.try_get_mut(source)?
+            .for_each(&mut |value| merge_patch(value, patch.clone()));
+        Ok(())
+    }
+
+    /// Deep-merges `patches[i]` into element `i` of every focused array,
+    /// stopping at the shorter of the two: extra elements are left as they
+    /// are and extra patches are ignored.
+    pub fn merge_each(&self, source: &mut Value, patches: Vec<Value>) {
+        for leaf in self.get_all_mut(source) {
+            if let Value::Array(arr) = leaf {
+                for (value, patch) in arr.iter_mut().zip(&patches) {
+                    merge_patch(value, patch.clone());
+                }
+            }
+        }
+    }
+
+    /// Fills every focused object with the keys of `template` it lacks, at
+    /// any depth, without overwriting what's already there. Arrays, in the
+    /// focus or in `template`, are taken as a whole: an existing array is
+    /// kept and a missing one is copied from `template`.
+    pub fn apply_defaults(&self, source: &mut Value, template: &Value) {
+        for leaf in self.get_all_mut(source) {
+            fill_defaults(leaf, template);
+        }
+    }
+
+    /// Applies `ops` relative to the focus of `self`. The ops run against a
+    /// copy of `source`, which is only written back once every op succeeded,
+    /// so a failing op leaves `source` untouched.
+    pub fn transaction(&self, source: &mut Value, ops: Vec<LensOp>) -> Result<(), LensError> {
+        let mut working = source.clone();
+        for op in ops {
+            op.apply(self.try_get_mut(&mut working)?)?;
+        }
+        *source = working;
+        Ok(())
+    }
+
+    /// Applies a serializable [`Operation`] at the focus. `Get` returns a
+    /// copy of the focus, the edits return `None`. `Over` transforms every
+    /// leaf of a copy of `source`, which is only written back if all of them
+    /// could be transformed.
+    pub fn apply(&self, source: &mut Value, op: Operation) -> OpResult {
+        match op {
+            Operation::Get => self.try_get(source).map(|view| Some(view.into_value())),
+            Operation::Set(value) => self.try_set(source, value).map(|()| None),
+            Operation::Delete => self.try_delete(source).map(|()| None),
+            Operation::Merge(patch) => self.try_merge(source, patch).map(|()| None),
+            Operation::Over(transform) => {
+                let mut working = source.clone();
+                for leaf in self.try_get_mut(&mut working)?.into_vec() {
+                    transform.apply(leaf).map_err(|kind| self.error(kind))?;
+                }
+                *source = working;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn select<I: Select>(self, item: I) -> Self {
+        item.pipe(self)
+    }
+
+    /// Like `select`, but fails on a segment that doesn't parse instead of
+    /// taking it as a field name.
+    pub fn try_select<I: TrySelect>(self, item: I) -> Result<Self, SelectError> {
+        item.try_pipe(self)
+    }
+
+    pub fn new<I: Select>(item: I) -> Self {
+        item.pipe(Lens::Empty)
+    }
+
+    /// A single `Field` named `key`, taken literally: unlike
+    /// [`Lens::parse`], dots and brackets in `key` don't start new segments.
+    pub fn field_literal(key: &str) -> Self {
+        Lens::Field(key.to_string())
+    }
+
+    /// The key of a `Field` lens, `None` for any other kind of lens.
+    pub fn as_field(&self) -> Option<&str> {
+        match self {
+            Lens::Field(field) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// The index of an `Index` lens, `None` for any other kind of lens.
+    pub fn as_index(&self) -> Option<usize> {
+        match self {
+            Lens::Index(index) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Applies `f` to every leaf this lens focuses, as a [`Lens::Map`].
+    pub fn map<F: Fn(&Value) -> Value + Send + Sync + 'static>(self, f: F) -> Self {
+        Lens::Map(Box::new(self), MapFn::new(f))
+    }
+
+    /// A `Union` of the given sibling keys, focused in the order given.
+    pub fn fields(keys: &[&str]) -> Self {
+        Lens::Union(
+            keys.iter()
+                .map(|key| Lens::Field(key.to_string()))
+                .collect(),
+        )
+    }
+
+    /// A `Union` of the given indices, in the order they were given.
+    pub fn indices(indices: &[usize]) -> Self {
+        Lens::Union(indices.iter().copied().map(Lens::Index).collect())
+    }
+
+    /// Focuses the `content` field of an adjacently tagged enum such as
+    /// `{"t": "A", "c": {...}}`, but only when its `tag` field is
+    /// `tag_value`. This is a `Guard` on the tag.
+    pub fn adjacent(tag: &str, tag_value: &str, content: &str) -> Self {
+        Lens::Guard {
+            condition: Box::new(Lens::new(tag)),
+            expected: Value::String(tag_value.to_string()),
+            inner: Box::new(Lens::new(content)),
+        }
+    }
+
+    pub fn foreach() -> Self {
+        Lens::ForEach
+    }
+
+    pub fn each(self) -> Self {
+        Lens::ForEach.pipe(self)
+    }
+
+    /// Removes the `Empty` operands of hand-built `Compose`s, which `select`
+    /// never produces, including inside nested lenses.
+    pub fn normalize(self) -> Lens {
+        match self {
+            Lens::Compose(first, second) => first.normalize().select(second.normalize()),
+            Lens::Coalesce(lenses) => {
+                Lens::Coalesce(lenses.into_iter().map(Lens::normalize).collect())
+            }
+            Lens::Union(lenses) => Lens::Union(lenses.into_iter().map(Lens::normalize).collect()),
+            Lens::Embedded(inner) => Lens::Embedded(Box::new(inner.normalize())),
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => Lens::Base64(Box::new(inner.normalize())),
+            Lens::Cast(inner, ty) => Lens::Cast(Box::new(inner.normalize()), ty),
+            Lens::Map(inner, f) => Lens::Map(Box::new(inner.normalize()), f),
+            Lens::Guard {
+                condition,
+                expected,
+                inner,
+            } => Lens::Guard {
+                condition: Box::new(condition.normalize()),
+                expected,
+                inner: Box::new(inner.normalize()),
+            },
+            lens => lens,
+        }
+    }
+
+    /// Number of segments along the longest path the lens can descend.
+    /// `Empty` has depth zero and a `Coalesce` or `Union` is as deep as its
+    /// deepest alternative.
+    pub fn depth(&self) -> usize {
+        match self {
+            Lens::Field(_)
+            | Lens::FieldCI(_)
+            | Lens::Index(_)
+            | Lens::ForEach
+            | Lens::Take(_)
+            | Lens::Skip(_)
+            | Lens::Wildcard
+            | Lens::Glob(_)
+            | Lens::ById { .. }
+            | Lens::ByIds { .. }
+            | Lens::Length => 1,
+            Lens::Compose(first, second) => first.depth() + second.depth(),
+            Lens::Coalesce(lenses) | Lens::Union(lenses) => {
+                lenses.iter().map(Lens::depth).max().unwrap_or(0)
+            }
+            Lens::Embedded(inner) => 1 + inner.depth(),
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => 1 + inner.depth(),
+            Lens::Cast(inner, _) | Lens::Map(inner, _) | Lens::Guard { inner, .. } => inner.depth(),
+            Lens::DescendLimited { max_depth, .. } => *max_depth,
+            Lens::Empty => 0,
+        }
+    }
+
+    /// Total number of segments in the lens, counting every alternative of a
+    /// `Coalesce` or `Union`.
+    pub fn complexity(&self) -> usize {
+        match self {
+            Lens::Field(_)
+            | Lens::FieldCI(_)
+            | Lens::Index(_)
+            | Lens::ForEach
+            | Lens::Take(_)
+            | Lens::Skip(_)
+            | Lens::Wildcard
+            | Lens::Glob(_)
+            | Lens::ById { .. }
+            | Lens::ByIds { .. }
+            | Lens::Length
+            | Lens::DescendLimited { .. } => 1,
+            Lens::Compose(first, second) => first.complexity() + second.complexity(),
+            Lens::Coalesce(lenses) | Lens::Union(lenses) => {
+                lenses.iter().map(Lens::complexity).sum()
+            }
+            Lens::Embedded(inner) => 1 + inner.complexity(),
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => 1 + inner.complexity(),
+            Lens::Cast(inner, _) | Lens::Map(inner, _) => inner.complexity(),
+            Lens::Guard {
+                condition, inner, ..
+            } => condition.complexity() + inner.complexity(),
+            Lens::Empty => 0,
+        }
+    }
+
+    /// Returns `true` if every path focused by `other` starts with the
+    /// segments of `self`. A `ForEach` in `self` matches any `Index` (or
+    /// `ForEach`) at the same position in `other`, while an `Index` never
+    /// matches a `ForEach` since the latter reaches beyond that single index.
+    pub fn is_prefix_of(&self, other: &Lens) -> bool {
+        let prefix = self.parts();
+        let parts = other.parts();
+        prefix.len() <= parts.len()
+            && prefix.iter().zip(parts).all(|(a, b)| match (a, b) {
+                (Lens::ForEach, Lens::Index(_)) | (Lens::Wildcard, Lens::Field(_)) => true,
+                (Lens::Glob(pattern), Lens::Field(field)) => glob_match(pattern, field),
+                (a, b) => *a == b,
+            })
+    }
+
+    /// Expands every `ForEach` against `value`, returning one concrete lens
+    /// made only of `Field` and `Index` segments per focused leaf.
+    pub fn resolve(&self, value: &Value) -> Vec<Lens> {
+        let mut lenses = Vec::new();
+        let _ = self.traverse(value, &mut Vec::new(), &mut |path, _| {
+            lenses.push(Lens::from_segments(path));
+            ControlFlow::Continue(())
+        });
+        lenses
+    }
+
+    /// Calls `f` with the concrete path and value of every leaf the lens
+    /// focuses, in document order. The depth of a leaf is the length of its
+    /// path. Leaves inside `Embedded` documents have no path and are skipped.
+    pub fn walk<F: FnMut(&[Segment], &Value)>(&self, value: &Value, mut f: F) {
+        let _ = self.traverse(value, &mut Vec::new(), &mut |path, value| {
+            f(path, value);
+            ControlFlow::Continue(())
+        });
+    }
+
+    /// Like [`Lens::walk`], with a mutable reference to each leaf. The paths
+    /// are resolved up front and each leaf is borrowed only for its own call,
+    /// so no two borrows overlap. A path that an earlier call made
+    /// unreachable is skipped.
+    pub fn walk_mut<F: FnMut(&[Segment], &mut Value)>(&self, value: &mut Value, mut f: F) {
+        let mut paths = Vec::new();
+        let _ = self.traverse(value, &mut Vec::new(), &mut |path, _| {
+            paths.push(path.to_vec());
+            ControlFlow::Continue(())
+        });
+        for path in paths {
+            if let Some(Modify::BorrowMut(leaf)) = Lens::from_segments(&path).get_mut(value) {
+                f(&path, leaf);
+            }
+        }
+    }
+
+    /// Pairs every leaf with the index of the array element it came from,
+    /// i.e. the last index along its concrete path. For a lens with nested
+    /// `ForEach`es that's the innermost index; [`Lens::walk`] gives the full
+    /// path. Leaves reached without passing through an array are skipped.
+    pub fn get_indexed<'a>(&'a self, value: &'a Value) -> Vec<(usize, &'a Value)> {
+        let mut leaves = Vec::new();
+        let _ = self.traverse(value, &mut Vec::new(), &mut |path, value| {
+            if let Some(index) = last_index(path) {
+                leaves.push((index, value));
+            }
+            ControlFlow::Continue(())
+        });
+        leaves
+    }
+
+    /// Applies the part of the lens after its first `ForEach` to every
+    /// element of the array before it, keeping one entry per element: `None`
+    /// where the rest of the lens misses, or doesn't focus a single borrowed
+    /// value. Empty if the lens has no `ForEach` or the part before it
+    /// doesn't focus a single array.
+    pub fn get_each_aligned<'a>(&'a self, value: &'a Value) -> Vec<Option<&'a Value>> {
+        let parts = self.parts();
+        let Some(split) = parts.iter().position(|part| **part == Lens::ForEach) else {
+            return Vec::new();
+        };
+        let (prefix, rest) = (&parts[..split], &parts[split + 1..]);
+        let get = |parts: &[&'a Lens], value: &'a Value| {
+            parts
+                .iter()
+                .try_fold(View::Borrow(value), |view, part| view.get(part))
+        };
+        match get(prefix, value) {
+            Some(View::Borrow(Value::Array(elements))) => elements
+                .iter()
+                .map(|element| match get(rest, element)? {
+                    View::Borrow(value) => Some(value),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Splits the elements of a single array focus into those satisfying
+    /// `f` and the rest, cloned and in order. Both are empty if the focus
+    /// isn't an array.
+    pub fn partition<F: Fn(&Value) -> bool>(
+        &self,
+        value: &Value,
+        f: F,
+    ) -> (Vec<Value>, Vec<Value>) {
+        match self.get(value) {
+            Some(View::Borrow(Value::Array(arr))) => {
+                arr.iter().cloned().partition(|value| f(value))
+            }
+            Some(View::Owned(Value::Array(arr))) => arr.into_iter().partition(|value| f(value)),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Returns the first key, in iteration order, of a focused object whose
+    /// value satisfies `f`. A multi-focus is searched leaf by leaf.
+    pub fn find_key<F: Fn(&Value) -> bool>(&self, value: &Value, f: F) -> Option<String> {
+        let mut found = None;
+        let _ = self.traverse(value, &mut Vec::new(), &mut |_, value| {
+            let key = value
+                .as_object()
+                .and_then(|obj| obj.iter().find(|(_, value)| f(value)));
+            match key {
+                Some((key, _)) => {
+                    found = Some(key.clone());
+                    ControlFlow::Break(())
+                }
+                None => ControlFlow::Continue(()),
+            }
+        });
+        found
+    }
+
+    /// Builds an object at the focus mapping each name in `fields` to the
+    /// value its lens resolves to, skipping lenses that don't resolve. A
+    /// multi-focus produces an array of such objects and a missing focus
+    /// produces `null`.
+    pub fn project(&self, value: &Value, fields: &[(&str, Lens)]) -> Value {
+        self.project_checked(value, fields).0
+    }
+
+    /// Like `project`, but also returns the names whose lens didn't resolve,
+    /// in the order of `fields`. Under a multi-focus a name is reported once
+    /// if it missed in any of the objects, and a missing focus reports all
+    /// of them.
+    pub fn project_checked(&self, value: &Value, fields: &[(&str, Lens)]) -> (Value, Vec<String>) {
+        let mut missed = vec![true; fields.len()];
+        let projected = match self.get(value) {
+            Some(view) => {
+                missed.fill(false);
+                project_view(view, fields, &mut missed)
+            }
+            None => Value::Null,
+        };
+        let missed = fields
+            .iter()
+            .zip(missed)
+            .filter(|(_, missed)| *missed)
+            .map(|((name, _), _)| name.to_string())
+            .collect();
+        (projected, missed)
+    }
+
+    /// Groups the elements of the focused array by the string `key` resolves
+    /// to in each of them, as an object mapping each key to its elements in
+    /// order. Numbers and bools are rendered as strings; elements whose key
+    /// is missing, `null` or not a scalar go under `""`. Yields `null` if the
+    /// focus isn't a single array.
+    pub fn group_by(&self, value: &Value, key: &Lens) -> Value {
+        let Some(View::Borrow(Value::Array(elements))) = self.get(value) else {
+            return Value::Null;
+        };
+        let mut groups = Map::new();
+        for element in elements {
+            let name = match key.get(element).map(View::into_value) {
+                Some(Value::String(name)) => name,
+                Some(scalar @ (Value::Number(_) | Value::Bool(_))) => scalar.to_string(),
+                _ => String::new(),
+            };
+            if let Value::Array(group) = groups
+                .entry(name)
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                group.push(element.clone());
+            }
+        }
+        Value::Object(groups)
+    }
+
+    /// Visits every leaf focused by the lens in document order together with
+    /// its concrete path, stopping as soon as `f` breaks.
+    pub(crate) fn traverse<'a>(
+        &'a self,
+        value: &'a Value,
+        path: &mut Vec<Segment>,
+        f: &mut dyn FnMut(&mut Vec<Segment>, &'a Value) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        match self {
+            Lens::Field(field) => match value.as_object().and_then(|obj| obj.get(field)) {
+                Some(value) => visit(path, Segment::Field(field.clone()), value, f),
+                None => ControlFlow::Continue(()),
+            },
+            Lens::FieldCI(field) => match value.as_object().and_then(|obj| {
+                let key = find_ci(obj, field)?;
+                Some((key, &obj[key]))
+            }) {
+                Some((key, value)) => visit(path, Segment::Field(key.clone()), value, f),
+                None => ControlFlow::Continue(()),
+            },
+            Lens::Index(index) => match value.as_array().and_then(|arr| {
+                let index = array_index(*index, arr.len());
+                Some((index, arr.get(index)?))
+            }) {
+                Some((index, value)) => visit(path, Segment::Index(index), value, f),
+                None => ControlFlow::Continue(()),
+            },
+            Lens::Compose(first, second) => first.traverse(value, path, &mut |path, value| {
+                second.traverse(value, path, f)
+            }),
+            Lens::ForEach => {
+                for (index, value) in value.as_array().into_iter().flatten().enumerate() {
+                    visit(path, Segment::Index(index), value, f)?;
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::Take(n) => {
+                let values = value.as_array().into_iter().flatten();
+                for (index, value) in values.enumerate().take(*n) {
+                    visit(path, Segment::Index(index), value, f)?;
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::Skip(n) => {
+                let values = value.as_array().into_iter().flatten();
+                for (index, value) in values.enumerate().skip(*n) {
+                    visit(path, Segment::Index(index), value, f)?;
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::Wildcard => {
+                for (key, value) in value.as_object().into_iter().flatten() {
+                    visit(path, Segment::Field(key.clone()), value, f)?;
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::Glob(pattern) => {
+                for (key, value) in value.as_object().into_iter().flatten() {
+                    if glob_match(pattern, key) {
+                        visit(path, Segment::Field(key.clone()), value, f)?;
+                    }
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::Coalesce(lenses) => {
+                let chosen = choose(lenses, value, true).or_else(|| choose(lenses, value, false));
+                match chosen {
+                    Some(lens) => lens.traverse(value, path, f),
+                    None => ControlFlow::Continue(()),
+                }
+            }
+            Lens::Union(lenses) => {
+                for lens in lenses {
+                    lens.traverse(value, path, f)?;
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::ById { field, value: id } => match value.as_array().and_then(|arr| {
+                let index = position_by_id(arr, field, id)?;
+                Some((index, &arr[index]))
+            }) {
+                Some((index, value)) => visit(path, Segment::Index(index), value, f),
+                None => ControlFlow::Continue(()),
+            },
+            Lens::ByIds { field, values } => {
+                if let Some(arr) = value.as_array() {
+                    for index in positions_by_ids(arr, field, values) {
+                        visit(path, Segment::Index(index), &arr[index], f)?;
+                    }
+                }
+                ControlFlow::Continue(())
+            }
+            // Leaves of an embedded document don't live in `value`.
+            Lens::Embedded(_) => ControlFlow::Continue(()),
+            #[cfg(feature = "base64")]
+            Lens::Base64(_) => ControlFlow::Continue(()),
+            // Nor do coerced leaves.
+            Lens::Cast(..) | Lens::Map(..) | Lens::Length => ControlFlow::Continue(()),
+            Lens::Guard { inner, .. } if self.check_guard(value).is_ok() => {
+                inner.traverse(value, path, f)
+            }
+            Lens::Guard { .. } => ControlFlow::Continue(()),
+            Lens::DescendLimited { key, max_depth } => {
+                for (segments, value) in descend(value, key, *max_depth) {
+                    let len = path.len();
+                    path.extend(segments);
+                    let flow = f(path, value);
+                    path.truncate(len);
+                    flow?;
+                }
+                ControlFlow::Continue(())
+            }
+            Lens::Empty => f(path, value),
+        }
+    }
+
+    /// Whether every leaf of the lens borrows from the input document.
+    fn borrows(&self) -> bool {
+        match self {
+            Lens::Compose(first, second) => first.borrows() && second.borrows(),
+            Lens::Coalesce(lenses) | Lens::Union(lenses) => lenses.iter().all(Lens::borrows),
+            Lens::Embedded(_) | Lens::Cast(..) | Lens::Map(..) | Lens::Length => false,
+            Lens::Guard { inner, .. } => inner.borrows(),
+            #[cfg(feature = "base64")]
+            Lens::Base64(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Like `traverse`, but without tracking paths, so it doesn't allocate.
+    fn leaves<'a>(&'a self, value: &'a Value, f: &mut dyn FnMut(&'a Value)) {
+        match self {
+            Lens::Field(field) => value
+                .as_object()
+                .and_then(|obj| obj.get(field))
+                .into_iter()
+                .for_each(f),
+            Lens::FieldCI(field) => value
+                .as_object()
+                .and_then(|obj| Some(&obj[find_ci(obj, field)?]))
+                .into_iter()
+                .for_each(f),
+            Lens::Index(index) => value
+                .as_array()
+                .and_then(|arr| arr.get(array_index(*index, arr.len())))
+                .into_iter()
+                .for_each(f),
+            Lens::Compose(first, second) => {
+                first.leaves(value, &mut |value| second.leaves(value, f))
+            }
+            Lens::ForEach => value.as_array().into_iter().flatten().for_each(f),
+            Lens::Take(n) => value.as_array().into_iter().flatten().take(*n).for_each(f),
+            Lens::Skip(n) => value.as_array().into_iter().flatten().skip(*n).for_each(f),
+            Lens::Wildcard => value
+                .as_object()
+                .into_iter()
+                .flat_map(|obj| obj.values())
+                .for_each(f),
+            Lens::Glob(pattern) => value
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(key, _)| glob_match(pattern, key))
+                .for_each(|(_, value)| f(value)),
+            Lens::Coalesce(lenses) => {
+                let chosen = choose(lenses, value, true).or_else(|| choose(lenses, value, false));
+                if let Some(lens) = chosen {
+                    lens.leaves(value, f);
+                }
+            }
+            Lens::Union(lenses) => lenses.iter().for_each(|lens| lens.leaves(value, f)),
+            Lens::ById { field, value: id } => {
+                if let Some(arr) = value.as_array() {
+                    if let Some(index) = position_by_id(arr, field, id) {
+                        f(&arr[index]);
+                    }
+                }
+            }
+            Lens::ByIds { field, values } => {
+                if let Some(arr) = value.as_array() {
+                    for index in positions_by_ids(arr, field, values) {
+                        f(&arr[index]);
+                    }
+                }
+            }
+            Lens::Embedded(_) | Lens::Cast(..) | Lens::Map(..) | Lens::Length => {}
+            Lens::Guard { inner, .. } => {
+                if self.check_guard(value).is_ok() {
+                    inner.leaves(value, f);
+                }
+            }
+            #[cfg(feature = "base64")]
+            Lens::Base64(_) => {}
+            Lens::DescendLimited { key, max_depth } => descend(value, key, *max_depth)
+                .into_iter()
+                .for_each(|(_, value)| f(value)),
+            Lens::Empty => f(value),
+        }
+    }
+
+    /// Fails with `GuardFailed` unless `self` is a `Guard` whose condition
+    /// holds on `value`.
+    fn check_guard(&self, value: &Value) -> Result<(), LensError> {
+        let holds = match self {
+            Lens::Guard {
+                condition,
+                expected,
+                ..
+            } => condition
+                .get(value)
+                .is_some_and(|view| view.as_value() == Some(expected)),
+            _ => false,
+        };
+        if holds {
+            Ok(())
+        } else {
+            Err(self.error(LensErrorKind::GuardFailed))
+        }
+    }
+
+    pub(crate) fn error(&self, kind: LensErrorKind) -> LensError {
+        LensError {
+            path: self.to_string(),
+            kind,
+        }
+    }
+
+    fn mismatch(&self, expected: &'static str, found: &Value) -> LensError {
+        self.error(LensErrorKind::mismatch(expected, found))
+    }
+
+    /// Flattens nested `Compose`s into their segments, dropping `Empty`.
+    pub(crate) fn parts(&self) -> Vec<&Lens> {
+        match self {
+            Lens::Compose(first, second) => {
+                let mut parts = first.parts();
+                parts.extend(second.parts());
+                parts
+            }
+            Lens::Empty => Vec::new(),
+            lens => vec![lens],
+        }
+    }
+}
+
+/// Renders path segments as `a.b[0][*]`, quoting fields that aren't plain
+/// identifiers as `["a.b"]`. Variants without a path syntax render in a
+/// function-call style, e.g. `coalesce(a, b)`.
+impl fmt::Display for Lens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lens::Field(field) if is_identifier(field) => write!(f, "{}", field),
+            Lens::Field(field) => write!(f, "[{}]", Value::String(field.clone())),
+            Lens::FieldCI(field) => write!(f, "ci({})", Lens::Field(field.clone())),
+            Lens::Index(index) => write!(f, "[{}]", index),
+            Lens::Compose(first, second) => {
+                write!(f, "{}", join_path(&first.to_string(), &second.to_string()))
+            }
+            Lens::ForEach => write!(f, "[*]"),
+            Lens::Take(n) => write!(f, "[:{}]", n),
+            Lens::Skip(n) => write!(f, "[{}:]", n),
+            Lens::Wildcard => write!(f, "*"),
+            Lens::Length => write!(f, "#"),
+            Lens::Glob(pattern) => write!(f, "glob({})", Value::String(pattern.clone())),
+            Lens::Coalesce(lenses) => {
+                let lenses: Vec<_> = lenses.iter().map(Lens::to_string).collect();
+                write!(f, "coalesce({})", lenses.join(", "))
+            }
+            Lens::Union(lenses) => {
+                let lenses: Vec<_> = lenses.iter().map(Lens::to_string).collect();
+                write!(f, "union({})", lenses.join(", "))
+            }
+            Lens::Embedded(inner) => write!(f, "embedded({})", inner),
+            #[cfg(feature = "base64")]
+            Lens::Base64(inner) => write!(f, "base64({})", inner),
+            Lens::Cast(inner, ty) => write!(f, "cast({}, {})", inner, ty),
+            Lens::ById { field, value } => {
+                write!(f, "by_id({} == {})", Lens::Field(field.clone()), value)
+            }
+            Lens::ByIds { field, values } => write!(
+                f,
+                "by_ids({} in {})",
+                Lens::Field(field.clone()),
+                Value::Array(values.clone())
+            ),
+            Lens::Map(inner, _) => write!(f, "map({})", inner),
+            Lens::Guard {
+                condition,
+                expected,
+                inner,
+            } => write!(f, "guard({} == {}, {})", condition, expected, inner),
+            Lens::DescendLimited { key, max_depth } => {
+                write!(f, "descend({}, {})", Lens::Field(key.clone()), max_depth)
+            }
+            Lens::Empty => Ok(()),
+        }
+    }
+}
+
+fn mark(changed: &mut Option<&mut bool>) {
+    if let Some(changed) = changed {
+        **changed = true;
+    }
+}
+
+/// Writes `target` into `slot`, skipping equal values when `changed` tracks
+/// writes for [`Lens::set_if_changed`].
+fn assign(slot: &mut Value, target: Value, changed: &mut Option<&mut bool>) {
+    if changed.is_none() || *slot != target {
+        *slot = target;
+        mark(changed);
+    }
+}
+
+fn insert(obj: &mut Map<String, Value>, key: &str, target: Value, changed: &mut Option<&mut bool>) {
+    match obj.get_mut(key) {
+        Some(slot) => assign(slot, target, changed),
+        None => {
+            obj.insert(key.to_string(), target);
+            mark(changed);
+        }
+    }
+}
+
+/// Writes through `inner` into a decoded `document`, returning whether it
+/// has to be encoded back: always for a plain write, and only if a leaf
+/// changed when `changed` tracks writes.
+fn write_inside(
+    inner: &Lens,
+    document: &mut Value,
+    target: Value,
+    changed: &mut Option<&mut bool>,
+) -> Result<bool, LensError> {
+    let mut inner_changed = false;
+    let mut track = changed.is_some().then_some(&mut inner_changed);
+    inner.write(document, target, &mut track)?;
+    let encode = changed.is_none() || inner_changed;
+    if encode {
+        mark(changed);
+    }
+    Ok(encode)
+}
+
+/// Maps the `Lens::LAST` sentinel to the last index of a non-empty array.
+pub(crate) fn array_index(index: usize, len: usize) -> usize {
+    if index == Lens::LAST && len > 0 {
+        len - 1
+    } else {
+        index
+    }
+}
+
+/// `number + by` as [`Lens::increment`] computes it, `None` if the sum
+/// isn't a finite number. Integers are added as `u64` or `i64` so that
+/// values beyond the exact range of `f64` don't lose precision.
+pub(crate) fn add_number(number: &Number, by: f64) -> Option<Number> {
+    let sum = match (number.as_u64(), number.as_i64()) {
+        _ if by.fract() != 0.0 || by.abs() >= i64::MAX as f64 => None,
+        (Some(uint), _) if by >= 0.0 => uint.checked_add(by as u64).map(Number::from),
+        (_, Some(int)) => int.checked_add(by as i64).map(Number::from),
+        (Some(uint), _) => uint
+            .checked_sub((by as i64).unsigned_abs())
+            .map(Number::from),
+        _ => None,
+    };
+    sum.or_else(|| Number::from_f64(number.as_f64()? + by))
+}
+
+/// Whether `text` matches the glob `pattern` as a whole. On a mismatch the
+/// last `*` swallows one more character and matching resumes after it;
+/// earlier `*`s never need to be revisited, so there's no recursion.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some('?') => (p, t) = (p + 1, t + 1),
+            Some(c) if *c == text[t] => (p, t) = (p + 1, t + 1),
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    (p, t) = (star_p + 1, star_t + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Whether `part` is a `Field`, `FieldCI` or `Index`, which [`step`] and
+/// [`step_mut`] resolve.
+pub(crate) fn is_step(part: &Lens) -> bool {
+    matches!(part, Lens::Field(_) | Lens::FieldCI(_) | Lens::Index(_))
+}
+
+/// Applies a single `Field`, `FieldCI` or `Index` to `value` with the same
+/// semantics as `get`, `None` if it misses or `part` is any other lens.
+pub(crate) fn step<'a>(part: &Lens, value: &'a Value) -> Option<&'a Value> {
+    match (part, value) {
+        (Lens::Field(field), Value::Object(obj)) => obj.get(field),
+        (Lens::FieldCI(field), Value::Object(obj)) => obj.get(find_ci(obj, field)?),
+        (Lens::Index(index), Value::Array(arr)) => arr.get(array_index(*index, arr.len())),
+        _ => None,
+    }
+}
+
+/// Like [`step`], borrowing mutably.
+pub(crate) fn step_mut<'a>(part: &Lens, value: &'a mut Value) -> Option<&'a mut Value> {
+    match (part, value) {
+        (Lens::Field(field), Value::Object(obj)) => obj.get_mut(field),
+        (Lens::FieldCI(field), Value::Object(obj)) => {
+            let key = find_ci(obj, field)?.clone();
+            obj.get_mut(&key)
+        }
+        (Lens::Index(index), Value::Array(arr)) => {
+            let len = arr.len();
+            arr.get_mut(array_index(*index, len))
+        }
+        _ => None,
+    }
+}
+
+/// The first key of `obj` equal to `field` ignoring case.
+pub(crate) fn find_ci<'a>(obj: &'a Map<String, Value>, field: &str) -> Option<&'a String> {
+    find_lowered(obj, &field.to_lowercase())
+}
+
+/// Like [`find_ci`], with `lowered` already lowercased.
+pub(crate) fn find_lowered<'a>(obj: &'a Map<String, Value>, lowered: &str) -> Option<&'a String> {
+    obj.keys().find(|key| key.to_lowercase() == lowered)
+}
+
+fn is_identifier(field: &str) -> bool {
+    !field.is_empty()
+        && field
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn interpolate(template: &str, vars: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start..start + len + 3];
+        out.push_str(&rest[..start]);
+        match vars.get(&placeholder[2..placeholder.len() - 1]) {
+            Some(var) => out.push_str(var),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + len + 3..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn last_index(path: &[Segment]) -> Option<usize> {
+    path.iter().rev().find_map(|segment| match segment {
+        Segment::Index(index) => Some(*index),
+        Segment::Field(_) => None,
+    })
+}
+
+/// The index of the first element of `arr` whose `field` equals `id`.
+fn position_by_id(arr: &[Value], field: &str, id: &Value) -> Option<usize> {
+    arr.iter()
+        .position(|element| element.get(field) == Some(id))
+}
+
+/// The indices of the elements of `arr` matched by each of `ids` in turn,
+/// without duplicates.
+fn positions_by_ids(arr: &[Value], field: &str, ids: &[Value]) -> Vec<usize> {
+    let mut positions = Vec::new();
+    for id in ids {
+        if let Some(index) = position_by_id(arr, field, id) {
+            if !positions.contains(&index) {
+                positions.push(index);
+            }
+        }
+    }
+    positions
+}
+
+fn set_parts_through_null(parts: &[&Lens], source: &mut Value, target: Value) {
+    let Some((part, rest)) = parts.split_first() else {
+        *source = target;
+        return;
+    };
+    if source.is_null() && matches!(part, Lens::Field(_) | Lens::FieldCI(_)) {
+        *source = Value::Object(Map::new());
+    }
+    if rest.is_empty() {
+        return part.set(source, target);
+    }
+    for child in part.get_all_mut(source) {
+        set_parts_through_null(rest, child, target.clone());
+    }
+}
+
+fn set_parts_with_policy(parts: &[&Lens], source: &mut Value, target: Value, policy: IndexPolicy) {
+    let Some((part, rest)) = parts.split_first() else {
+        *source = target;
+        return;
+    };
+    if !rest.is_empty() {
+        for child in part.get_all_mut(source) {
+            set_parts_with_policy(rest, child, target.clone(), policy);
+        }
+        return;
+    }
+    match (part, source) {
+        (Lens::Index(index), Value::Array(arr)) if array_index(*index, arr.len()) >= arr.len() => {
+            match policy {
+                IndexPolicy::Ignore => {}
+                IndexPolicy::Append => arr.push(target),
+                IndexPolicy::Fill if *index == Lens::LAST => arr.push(target),
+                IndexPolicy::Fill => {
+                    if arr.try_reserve(*index + 1 - arr.len()).is_ok() {
+                        arr.resize(*index, Value::Null);
+                        arr.push(target);
+                    }
+                }
+            }
+        }
+        (part, source) => part.set(source, target),
+    }
+}
+
+fn ensure_parts(parts: &[&Lens], source: &mut Value, default: Value) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return false;
+    };
+    if source.is_null() {
+        // Only keep the new container if something was created inside it.
+        let mut container = match part {
+            Lens::Field(_) => Value::Object(Map::new()),
+            Lens::Index(_) => Value::Array(Vec::new()),
+            _ => return false,
+        };
+        let created = ensure_parts(parts, &mut container, default);
+        if created {
+            *source = container;
+        }
+        return created;
+    }
+    match (part, source) {
+        (Lens::Field(field), Value::Object(obj)) => match obj.get_mut(field) {
+            Some(_) if rest.is_empty() => false,
+            Some(child) => ensure_parts(rest, child, default),
+            None if rest.is_empty() => {
+                obj.insert(field.clone(), default);
+                true
+            }
+            None => {
+                let mut child = Value::Null;
+                let created = ensure_parts(rest, &mut child, default);
+                if created {
+                    obj.insert(field.clone(), child);
+                }
+                created
+            }
+        },
+        (Lens::Index(index), Value::Array(arr)) => {
+            let index = array_index(*index, arr.len());
+            if index < arr.len() {
+                return !rest.is_empty() && ensure_parts(rest, &mut arr[index], default);
+            }
+            // An empty array has no last element to create.
+            if index == Lens::LAST || arr.try_reserve(index + 1 - arr.len()).is_err() {
+                return false;
+            }
+            let child = if rest.is_empty() {
+                default
+            } else {
+                let mut child = Value::Null;
+                if !ensure_parts(rest, &mut child, default) {
+                    return false;
+                }
+                child
+            };
+            arr.resize(index, Value::Null);
+            arr.push(child);
+            true
+        }
+        (Lens::ForEach, Value::Array(arr)) => {
+            let mut created = false;
+            for value in arr {
+                created |= ensure_parts(rest, value, default.clone());
+            }
+            created
+        }
+        (Lens::Wildcard, Value::Object(obj)) => {
+            let mut created = false;
+            for value in obj.values_mut() {
+                created |= ensure_parts(rest, value, default.clone());
+            }
+            created
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+fn insert_at(obj: &mut Map<String, Value>, field: &str, target: Value, position: InsertPosition) {
+    if obj.contains_key(field) {
+        obj.insert(field.to_string(), target);
+        return;
+    }
+    let index = match position {
+        InsertPosition::First => 0,
+        InsertPosition::Last => obj.len(),
+        InsertPosition::Sorted => obj
+            .keys()
+            .position(|key| key.as_str() > field)
+            .unwrap_or(obj.len()),
+    };
+    obj.shift_insert(index, field.to_string(), target);
+}
+
+#[cfg(not(feature = "preserve_order"))]
+fn insert_at(obj: &mut Map<String, Value>, field: &str, target: Value, _: InsertPosition) {
+    obj.insert(field.to_string(), target);
+}
+
+/// Pushes the leaves of `view` onto `views`, flattening multi-focus views at
+/// any depth.
+fn splice<'a>(views: &mut Vec<View<'a>>, view: View<'a>) {
+    match view {
+        View::BorrowVec(inner) => inner.into_iter().for_each(|view| splice(views, view)),
+        view => views.push(view),
+    }
+}
+
+/// Mutably borrows the focus of a union whose sub-lenses are all sibling
+/// `Field`s or all sibling `Index`es. Each slot is borrowed at most once, in
+/// the order the sub-lenses were given.
+fn union_mut<'a>(lenses: &[Lens], value: &'a mut Value) -> Option<Modify<'a>> {
+    let parts = lenses
+        .iter()
+        .map(|lens| match lens.parts().as_slice() {
+            [part] => Some(*part),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut found: Vec<(usize, &mut Value)> =
+        if parts.iter().all(|part| matches!(part, Lens::Field(_))) {
+            value
+                .as_object_mut()
+                .into_iter()
+                .flat_map(|obj| obj.iter_mut())
+                .filter_map(|(key, value)| {
+                    let position = parts
+                        .iter()
+                        .position(|part| matches!(part, Lens::Field(field) if field == key))?;
+                    Some((position, value))
+                })
+                .collect()
+        } else if parts.iter().all(|part| matches!(part, Lens::Index(_))) {
+            let len = value.as_array().map_or(0, Vec::len);
+            value
+                .as_array_mut()
+                .into_iter()
+                .flat_map(|arr| arr.iter_mut().enumerate())
+                .filter_map(|(index, value)| {
+                    let position = parts.iter().position(
+                        |part| matches!(part, Lens::Index(i) if array_index(*i, len) == index),
+                    )?;
+                    Some((position, value))
+                })
+                .collect()
+        } else {
+            return None;
+        };
+
+    found.sort_by_key(|(position, _)| *position);
+    Some(Modify::BorrowVec(
+        found
+            .into_iter()
+            .map(|(_, value)| Modify::BorrowMut(value))
+            .collect(),
+    ))
+}
+
+/// Projects every leaf of `view`, flagging in `missed` the fields that
+/// didn't resolve in at least one of them.
+fn project_view(view: View<'_>, fields: &[(&str, Lens)], missed: &mut [bool]) -> Value {
+    match view {
+        View::Borrow(value) => project_value(value, fields, missed),
+        View::BorrowVec(views) => Value::Array(
+            views
+                .into_iter()
+                .map(|view| project_view(view, fields, missed))
+                .collect(),
+        ),
+        View::Owned(value) => project_value(&value, fields, missed),
+    }
+}
+
+fn project_value(value: &Value, fields: &[(&str, Lens)], missed: &mut [bool]) -> Value {
+    Value::Object(
+        fields
+            .iter()
+            .zip(missed)
+            .filter_map(|((name, lens), missed)| {
+                let found = lens.get(value);
+                *missed |= found.is_none();
+                Some((name.to_string(), found?.into_value()))
+            })
+            .collect(),
+    )
+}
+
+fn visit<'a>(
+    path: &mut Vec<Segment>,
+    segment: Segment,
+    value: &'a Value,
+    f: &mut dyn FnMut(&mut Vec<Segment>, &'a Value) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    path.push(segment);
+    let flow = f(path, value);
+    path.pop();
+    flow
+}
+
+/// The values under `key` at most `max_depth` levels below `value`, with
+/// their paths, in document order. Children are pushed onto the stack in
+/// reverse so that they're popped in order.
+fn descend<'a>(value: &'a Value, key: &str, max_depth: usize) -> Vec<(Vec<Segment>, &'a Value)> {
+    let mut found = Vec::new();
+    let mut stack = vec![(Vec::new(), value, false)];
+    while let Some((path, value, matched)) = stack.pop() {
+        if matched {
+            found.push((path, value));
+            continue;
+        }
+        if path.len() >= max_depth {
+            continue;
+        }
+        let child = |segment| {
+            let mut path = path.clone();
+            path.push(segment);
+            path
+        };
+        match value {
+            Value::Object(obj) => stack.extend(
+                obj.iter()
+                    .map(|(k, v)| (child(Segment::Field(k.clone())), v, k == key))
+                    .rev()
+                    .collect::<Vec<_>>(),
+            ),
+            Value::Array(arr) => stack.extend(
+                arr.iter()
+                    .enumerate()
+                    .map(|(i, v)| (child(Segment::Index(i)), v, false))
+                    .rev()
+                    .collect::<Vec<_>>(),
+            ),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Like `descend`, but borrows the values mutably and without paths.
+fn descend_mut<'a>(value: &'a mut Value, key: &str, max_depth: usize) -> Vec<&'a mut Value> {
+    let mut found = Vec::new();
+    let mut stack = vec![(0, value, false)];
+    while let Some((depth, value, matched)) = stack.pop() {
+        if matched {
+            found.push(value);
+            continue;
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        match value {
+            Value::Object(obj) => stack.extend(
+                obj.iter_mut()
+                    .map(|(k, v)| (depth + 1, v, k == key))
+                    .rev()
+                    .collect::<Vec<_>>(),
+            ),
+            Value::Array(arr) => stack.extend(
+                arr.iter_mut()
+                    .map(|v| (depth + 1, v, false))
+                    .rev()
+                    .collect::<Vec<_>>(),
+            ),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Picks the first of `lenses` that resolves against `value`, optionally
+/// skipping those that resolve to `null`.
+fn choose<'a>(lenses: &'a [Lens], value: &Value, skip_null: bool) -> Option<&'a Lens> {
+    lenses.iter().find(|lens| match lens.get(value) {
+        Some(view) => !(skip_null && view.as_value().is_some_and(Value::is_null)),
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Edit, Transform};
+    use serde_json::json;
+
+    #[test]
+    fn test_field() {
+        let mut value = json!({"a": 1});
+        let lens = Lens::new("a");
+        let view = lens.get(&value).unwrap();
+        assert_eq!(view, View::Borrow(&json!(1)));
+
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"a": 2}));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut value = json!([1, 2, 3]);
+        let lens = Lens::new(1);
+        let view = lens.get(&value).unwrap();
+        assert_eq!(view, View::Borrow(&json!(2)));
+
+        lens.set(&mut value, json!(4));
+        assert_eq!(value, json!([1, 4, 3]));
+    }
+
+    #[test]
+    fn test_compose() {
+        let mut value = json!({"a": [1, 2, 3]});
+        let lens = Lens::new("a").select(1);
+        let view = lens.get(&value).unwrap();
+        assert_eq!(view, View::Borrow(&json!(2)));
+
+        lens.set(&mut value, json!(4));
+        assert_eq!(value, json!({"a": [1, 4, 3]}));
+    }
+
+    #[test]
+    fn test_for_each() {
+        let mut value = json!([{"a": 1}, {"a": 2}, {"a": 3}]);
+
+        let lens = Lens::foreach().select("a");
+        let view = lens.get(&value).unwrap();
+        assert_eq!(
+            view,
+            View::BorrowVec(vec![
+                View::Borrow(&json!(1)),
+                View::Borrow(&json!(2)),
+                View::Borrow(&json!(3))
+            ])
+        );
+
+        lens.set(&mut value, json!(4));
+        assert_eq!(value, json!([{"a": 4}, {"a": 4}, {"a": 4}]));
+    }
+    #[test]
+    fn test_deeply_nested() {
+        let mut value = json!({
+            "a": {
+                "b": {
+                    "c": [1, 2, {"d": 3}]
+                }
+            }
+        });
+
+        let lens = Lens::default()
+            .select("a")
+            .select("b")
+            .select("c")
+            .select(2)
+            .select("d");
+        let view = lens.get(&value).unwrap();
+        assert_eq!(view, View::Borrow(&json!(3)));
+
+        lens.set(&mut value, json!(4));
+        assert_eq!(
+            value,
+            json!({
+                "a": {
+                    "b": {
+                        "c": [1, 2, {"d": 4}]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_flatten_structure() {
+        let mut value = json!({
+            "a": {
+                "b": {
+                    "c": [1, 2, {"d": 3}]
+                }
+            }
+        });
+
+        let lens = Lens::default().select("a").select("b").select("c").each();
+        let view = lens.get(&value).unwrap();
+        assert_eq!(
+            view,
+            View::BorrowVec(vec![
+                View::Borrow(&json!(1)),
+                View::Borrow(&json!(2)),
+                View::Borrow(&json!({"d": 3}))
+            ])
+        );
+
+        lens.set(&mut value, json!(4));
+        assert_eq!(
+            value,
+            json!({
+                "a": {
+                    "b": {
+                        "c": [4, 4, 4]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_get() {
+        let value = json!({"a": [1, 2]});
+        assert_eq!(
+            Lens::new("b").try_get(&value).unwrap_err().kind,
+            LensErrorKind::MissingField("b".to_string())
+        );
+        assert_eq!(
+            Lens::new("a").select(5).try_get(&value).unwrap_err().kind,
+            LensErrorKind::IndexOutOfBounds { index: 5, len: 2 }
+        );
+        assert_eq!(
+            Lens::new("a").select("b").try_get(&value).unwrap_err().kind,
+            LensErrorKind::TypeMismatch {
+                expected: "object",
+                found: "array"
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_get_mut() {
+        let mut value = json!({"a": [1, 2]});
+        let err = Lens::new("a")
+            .select(5)
+            .try_get_mut(&mut value)
+            .unwrap_err();
+        assert_eq!(err.path, "a[5]");
+        assert_eq!(
+            err.kind,
+            LensErrorKind::IndexOutOfBounds { index: 5, len: 2 }
+        );
+        assert_eq!(
+            Lens::new("b").try_get_mut(&mut value).unwrap_err().kind,
+            LensErrorKind::MissingField("b".to_string())
+        );
+        assert_eq!(
+            Lens::new("a")
+                .select("b")
+                .try_get_mut(&mut value)
+                .unwrap_err(),
+            Lens::new("a").select("b").try_get(&value).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut value = json!({"a": {"b": 1, "c": 2}});
+        let lens = Lens::new("a");
+        let ops = vec![
+            LensOp::Set(Lens::new("b"), json!(10)),
+            LensOp::Delete(Lens::new("c")),
+            LensOp::Merge(Lens::default(), json!({"d": {"e": 3}})),
+        ];
+        lens.transaction(&mut value, ops).unwrap();
+        assert_eq!(value, json!({"a": {"b": 10, "d": {"e": 3}}}));
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let mut value = json!({"a": {"b": 1, "c": 2}});
+        let ops = vec![
+            LensOp::Set(Lens::new("a").select("b"), json!(10)),
+            LensOp::Delete(Lens::new("a").select("missing")),
+            LensOp::Set(Lens::new("a").select("c"), json!(20)),
+        ];
+        let result = Lens::default().transaction(&mut value, ops);
+        assert_eq!(
+            result,
+            Err(LensError {
+                path: "a.missing".to_string(),
+                kind: LensErrorKind::MissingField("missing".to_string())
+            })
+        );
+        assert_eq!(value, json!({"a": {"b": 1, "c": 2}}));
+    }
+
+    #[test]
+    fn test_transaction_rollback_multi_focus() {
+        let mut value = json!({"items": [{"n": 1}, 2, {"n": 3}]});
+        let ops = vec![LensOp::Set(Lens::ForEach.select("n"), json!(0))];
+        let result = Lens::new("items").transaction(&mut value, ops);
+        assert_eq!(
+            result,
+            Err(LensError {
+                path: "[*].n".to_string(),
+                kind: LensErrorKind::TypeMismatch {
+                    expected: "object",
+                    found: "number"
+                }
+            })
+        );
+        assert_eq!(value, json!({"items": [{"n": 1}, 2, {"n": 3}]}));
+    }
+
+    #[test]
+    fn test_apply_edit() {
+        let edit = Edit {
+            lens: Lens::new("a").select("b"),
+            op: Operation::Set(json!(10)),
+        };
+        let wire = serde_json::to_string(&edit).unwrap();
+        let edit: Edit = serde_json::from_str(&wire).unwrap();
+
+        let mut value = json!({"a": {"b": 1}});
+        assert_eq!(edit.apply(&mut value), Ok(None));
+        assert_eq!(value, json!({"a": {"b": 10}}));
+        assert_eq!(
+            Lens::new("a").apply(&mut value, Operation::Get),
+            Ok(Some(json!({"b": 10})))
+        );
+    }
+
+    #[test]
+    fn test_apply_over() {
+        let mut value = json!({"items": [{"n": 1}, {"n": "2"}]});
+        let lens = Lens::new("items").each().select("n");
+        let result = lens.apply(&mut value, Operation::Over(Transform::Increment(1.0)));
+        assert_eq!(
+            result.map_err(|err| err.kind),
+            Err(LensErrorKind::TypeMismatch {
+                expected: "number",
+                found: "string"
+            })
+        );
+        assert_eq!(value, json!({"items": [{"n": 1}, {"n": "2"}]}));
+
+        let cast = Operation::Over(Transform::Cast(JsonType::Number));
+        assert_eq!(lens.apply(&mut value, cast), Ok(None));
+        let increment = Operation::Over(Transform::Increment(1.0));
+        assert_eq!(lens.apply(&mut value, increment), Ok(None));
+        assert_eq!(value, json!({"items": [{"n": 2}, {"n": 3}]}));
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let lens = Lens::Coalesce(vec![
+            Lens::new("displayName"),
+            Lens::new("name"),
+            Lens::new("login"),
+        ]);
+
+        let mut value = json!({"displayName": null, "name": "Jane", "login": "jd"});
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!("Jane"))));
+
+        lens.set(&mut value, json!("Janet"));
+        assert_eq!(
+            value,
+            json!({"displayName": "Janet", "name": "Jane", "login": "jd"})
+        );
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!("Janet"))));
+    }
+
+    #[test]
+    fn test_coalesce_fallback() {
+        let lens = Lens::Coalesce(vec![Lens::new("displayName"), Lens::new("name")]);
+
+        let value = json!({"displayName": null});
+        assert_eq!(lens.get(&value), Some(View::Borrow(&Value::Null)));
+
+        let mut value = json!({});
+        assert_eq!(lens.get(&value), None);
+        lens.set(&mut value, json!("Jane"));
+        assert_eq!(value, json!({"displayName": "Jane"}));
+    }
+
+    #[test]
+    fn test_coalesce_skips_owned_null() {
+        let value = json!({"nickname": "null", "name": "Jane"});
+        let lens = Lens::Coalesce(vec![
+            Lens::new("nickname").select(Lens::Embedded(Box::new(Lens::Empty))),
+            Lens::new("name").map(|_| Value::Null),
+            Lens::new("name"),
+        ]);
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!("Jane"))));
+    }
+
+    #[test]
+    fn test_depth_and_complexity() {
+        assert_eq!(Lens::default().depth(), 0);
+        assert_eq!(Lens::default().complexity(), 0);
+
+        let lens = Lens::new("a").select("b").select(0);
+        assert_eq!(lens.depth(), 3);
+        assert_eq!(lens.complexity(), 3);
+
+        let lens = Lens::new("a").each().select("b");
+        assert_eq!(lens.depth(), 3);
+        assert_eq!(lens.complexity(), 3);
+
+        let lens = Lens::new("user").select(Lens::Coalesce(vec![
+            Lens::new("profile").select("name"),
+            Lens::new("login"),
+        ]));
+        assert_eq!(lens.depth(), 3);
+        assert_eq!(lens.complexity(), 4);
+    }
+
+    #[test]
+    fn test_is_prefix_of() {
+        let lens = Lens::new("user").select("profile").select("name");
+        assert!(Lens::new("user").select("profile").is_prefix_of(&lens));
+        assert!(lens.is_prefix_of(&lens));
+        assert!(Lens::default().is_prefix_of(&lens));
+        assert!(!Lens::new("user").select("settings").is_prefix_of(&lens));
+        assert!(!lens.is_prefix_of(&Lens::new("user").select("profile")));
+    }
+
+    #[test]
+    fn test_is_prefix_of_for_each() {
+        let lens = Lens::new("users").select(2).select("name");
+        assert!(Lens::new("users").each().is_prefix_of(&lens));
+        assert!(!Lens::new("users").select(1).is_prefix_of(&lens));
+        assert!(!Lens::new("users")
+            .select(2)
+            .is_prefix_of(&Lens::new("users").each()));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let value = json!([{"a": 1}, {"b": 2}, {"a": 3}]);
+        let lens = Lens::foreach().select("a");
+        assert_eq!(
+            lens.resolve(&value),
+            vec![Lens::new(0).select("a"), Lens::new(2).select("a")]
+        );
+
+        let value = json!([{"a": 1}, {"a": 2}]);
+        let lenses = Lens::default().each().select("a").resolve(&value);
+        assert_eq!(lenses.len(), 2);
+        assert_eq!(lenses[1].get(&value), Some(View::Borrow(&json!(2))));
+    }
+
+    #[test]
+    fn test_embedded() {
+        let mut value = json!({"payload": "{\"x\":1}"});
+        let lens = Lens::new("payload").select(Lens::Embedded(Box::new(Lens::new("x"))));
+        assert_eq!(lens.get(&value), Some(View::Owned(json!(1))));
+
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"payload": "{\"x\":2}"}));
+    }
+
+    #[test]
+    fn test_embedded_invalid_json() {
+        let mut value = json!({"payload": "not json"});
+        let lens = Lens::new("payload").select(Lens::Embedded(Box::new(Lens::new("x"))));
+        assert_eq!(lens.get(&value), None);
+        assert!(matches!(
+            lens.try_get(&value),
+            Err(LensError {
+                kind: LensErrorKind::Decode(_),
+                ..
+            })
+        ));
+
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"payload": "not json"}));
+    }
+
+    #[test]
+    fn test_embedded_error_path() {
+        let mut value = json!({"payload": "{\"a\":{\"b\":[1,2,3]}}"});
+        let inner = Lens::new("a").select("b").select(3);
+        let lens = Lens::new("payload").select(Lens::Embedded(Box::new(inner.clone())));
+        let miss = |path: &str| LensError {
+            path: path.to_string(),
+            kind: LensErrorKind::IndexOutOfBounds { index: 3, len: 3 },
+        };
+        assert_eq!(lens.try_get(&value), Err(miss("payload.embedded(a.b[3])")));
+        assert_eq!(
+            lens.try_set(&mut value, json!(4)),
+            Err(miss("payload.embedded(a.b[3])"))
+        );
+        assert_eq!(
+            lens.try_delete(&mut value),
+            Err(miss("payload.embedded(a.b[3])"))
+        );
+        assert_eq!(value, json!({"payload": "{\"a\":{\"b\":[1,2,3]}}"}));
+        assert_eq!(
+            Lens::Embedded(Box::new(inner)).to_string(),
+            "embedded(a.b[3])"
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        // base64 of `{"x":1}`
+        let mut value = json!({"payload": "eyJ4IjoxfQ=="});
+        let lens = Lens::new("payload").select(Lens::Base64(Box::new(Lens::new("x"))));
+        assert_eq!(lens.get(&value), Some(View::Owned(json!(1))));
+
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"payload": "eyJ4IjoyfQ=="}));
+        assert_eq!(lens.get(&value), Some(View::Owned(json!(2))));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_invalid() {
+        let lens = Lens::new("payload").select(Lens::Base64(Box::new(Lens::new("x"))));
+        // not base64, base64 of invalid UTF-8 and base64 of `not json`
+        for payload in ["%%%", "/w==", "bm90IGpzb24="] {
+            let mut value = json!({ "payload": payload });
+            assert_eq!(lens.get(&value), None);
+
+            lens.set(&mut value, json!(2));
+            assert_eq!(value, json!({ "payload": payload }));
+        }
+    }
+
+    #[test]
+    fn test_error_path() {
+        let value = json!({"a": {"b": [1, 2, 3]}});
+
+        let err = Lens::new("a")
+            .select("b")
+            .select(3)
+            .try_get(&value)
+            .unwrap_err();
+        assert_eq!(err.path, "a.b[3]");
+        assert_eq!(
+            err.kind,
+            LensErrorKind::IndexOutOfBounds { index: 3, len: 3 }
+        );
+        assert_eq!(
+            err.to_string(),
+            "index 3 out of bounds for array of length 3 at `a.b[3]`"
+        );
+
+        let err = Lens::new("a")
+            .select("c")
+            .select("d")
+            .try_get(&value)
+            .unwrap_err();
+        assert_eq!(err.path, "a.c");
+
+        let mut value = value;
+        let err = Lens::new("a")
+            .select("b")
+            .select(0)
+            .select("x")
+            .try_set(&mut value, json!(1))
+            .unwrap_err();
+        assert_eq!(err.path, "a.b[0].x");
+        assert_eq!(
+            err.kind,
+            LensErrorKind::TypeMismatch {
+                expected: "object",
+                found: "number"
+            }
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let lens = Lens::new("a").select("b.c").select(0).each().select("d");
+        assert_eq!(lens.to_string(), "a[\"b.c\"][0][*].d");
+    }
+
+    #[test]
+    fn test_project() {
+        let value = json!({"a": {"x": 1, "y": [2, 3], "z": 4}});
+        let fields = [
+            ("first", Lens::new("x")),
+            ("second", Lens::new("y")),
+            ("missing", Lens::new("w")),
+        ];
+        assert_eq!(
+            Lens::new("a").project(&value, &fields),
+            json!({"first": 1, "second": [2, 3]})
+        );
+        assert_eq!(Lens::new("b").project(&value, &fields), Value::Null);
+    }
+
+    #[test]
+    fn test_project_for_each() {
+        let value = json!([{"id": 1, "name": "a", "age": 3}, {"id": 2}]);
+        let fields = [("id", Lens::new("id")), ("name", Lens::new("name"))];
+        assert_eq!(
+            Lens::foreach().project(&value, &fields),
+            json!([{"id": 1, "name": "a"}, {"id": 2}])
+        );
+    }
+
+    #[test]
+    fn test_group_by() {
+        let value = json!({"users": [
+            {"name": "a", "role": "admin"},
+            {"name": "b", "role": "user"},
+            {"name": "c", "role": "admin"},
+            {"name": "d"},
+            {"name": "e", "role": 1}
+        ]});
+        assert_eq!(
+            Lens::new("users").group_by(&value, &Lens::new("role")),
+            json!({
+                "": [{"name": "d"}],
+                "1": [{"name": "e", "role": 1}],
+                "admin": [{"name": "a", "role": "admin"}, {"name": "c", "role": "admin"}],
+                "user": [{"name": "b", "role": "user"}]
+            })
+        );
+        assert_eq!(
+            Lens::new("missing").group_by(&value, &Lens::new("role")),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_project_checked() {
+        let value = json!({"user": {"id": 1, "name": "a"}});
+        let fields = [("id", Lens::new("id")), ("email", Lens::new("email"))];
+        assert_eq!(
+            Lens::new("user").project_checked(&value, &fields),
+            (json!({"id": 1}), vec!["email".to_string()])
+        );
+        assert_eq!(
+            Lens::new("missing").project_checked(&value, &fields),
+            (Value::Null, vec!["id".to_string(), "email".to_string()])
+        );
+
+        let value = json!([{"id": 1, "email": "x"}, {"id": 2}]);
+        let (_, missed) = Lens::foreach().project_checked(&value, &fields);
+        assert_eq!(missed, vec!["email".to_string()]);
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_set_at() {
+        fn keys(value: &Value) -> Vec<&str> {
+            value
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect()
+        }
+
+        let value = json!({"a": {"b": 1, "d": 2}});
+        let lens = Lens::new("a").select("c");
+
+        let mut first = value.clone();
+        lens.set_at(&mut first, json!(3), InsertPosition::First);
+        assert_eq!(keys(&first["a"]), ["c", "b", "d"]);
+
+        let mut last = value.clone();
+        lens.set_at(&mut last, json!(3), InsertPosition::Last);
+        assert_eq!(keys(&last["a"]), ["b", "d", "c"]);
+
+        let mut sorted = value.clone();
+        lens.set_at(&mut sorted, json!(3), InsertPosition::Sorted);
+        assert_eq!(keys(&sorted["a"]), ["b", "c", "d"]);
+
+        let mut existing = value.clone();
+        Lens::new("a")
+            .select("d")
+            .set_at(&mut existing, json!(3), InsertPosition::First);
+        assert_eq!(keys(&existing["a"]), ["b", "d"]);
+        assert_eq!(existing, json!({"a": {"b": 1, "d": 3}}));
+    }
+
+    #[test]
+    fn test_get_all_mut() {
+        let mut value = json!([{"a": 1}, {"b": 2}, {"a": 3}]);
+        let lens = Lens::foreach().select("a");
+
+        let refs = lens.get_all_mut(&mut value);
+        assert_eq!(refs.len(), 2);
+        for (i, value) in refs.into_iter().enumerate() {
+            *value = json!(i * 10);
+        }
+        assert_eq!(value, json!([{"a": 0}, {"b": 2}, {"a": 10}]));
+
+        assert!(Lens::new("missing").get_all_mut(&mut value).is_empty());
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut value = json!({"a": 1, "c": 3, "d": 4});
+        let lens = Lens::fields(&["a", "b", "c"]);
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!(1)),
+                View::Borrow(&json!(3))
+            ]))
+        );
+
+        lens.set(&mut value, json!(0));
+        assert_eq!(value, json!({"a": 0, "b": 0, "c": 0, "d": 4}));
+    }
+
+    #[test]
+    fn test_union_get_mut() {
+        let mut value = json!({"a": {"x": 1}, "b": {"x": 2}, "c": {"x": 3}});
+        let lens = Lens::fields(&["c", "a"]).select("x");
+        lens.set(&mut value, json!(0));
+        assert_eq!(value, json!({"a": {"x": 0}, "b": {"x": 2}, "c": {"x": 0}}));
+
+        let lens = Lens::Union(vec![Lens::new("a"), Lens::new("a").select("x")]);
+        assert!(lens.get_mut(&mut value).is_none());
+    }
+
+    #[test]
+    fn test_set_with() {
+        let mut value = json!({"a": {}});
+        let mut misses = Vec::new();
+        let lens = Lens::new("a").select("b").select("c");
+        lens.set_with(&mut value, json!(1), |path| misses.push(path.to_string()));
+        assert_eq!(misses, ["a.b"]);
+        assert_eq!(value, json!({"a": {}}));
+
+        Lens::new("a")
+            .select("b")
+            .set_with(&mut value, json!(1), |path| misses.push(path.to_string()));
+        assert_eq!(misses, ["a.b"]);
+        assert_eq!(value, json!({"a": {"b": 1}}));
+    }
+
+    #[test]
+    fn test_take_and_skip() {
+        let mut value = json!({"items": [{"id": 1}, {"id": 2}, {"id": 3}]});
+
+        let take = Lens::new("items").select(Lens::Take(2)).select("id");
+        assert_eq!(
+            take.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!(1)),
+                View::Borrow(&json!(2))
+            ]))
+        );
+
+        let skip = Lens::new("items").select(Lens::Skip(2)).select("id");
+        assert_eq!(
+            skip.get(&value),
+            Some(View::BorrowVec(vec![View::Borrow(&json!(3))]))
+        );
+
+        skip.set(&mut value, json!(0));
+        assert_eq!(value, json!({"items": [{"id": 1}, {"id": 2}, {"id": 0}]}));
+
+        Lens::Take(5).set(&mut value["items"], json!(null));
+        assert_eq!(value, json!({"items": [null, null, null]}));
+        assert_eq!(
+            Lens::Skip(5).get(&value["items"]),
+            Some(View::BorrowVec(vec![]))
+        );
+    }
+
+    #[test]
+    fn test_get_capped() {
+        let value = Value::Array((0..1000).map(|i| json!({ "a": i })).collect());
+        let lens = Lens::foreach().select("a");
+
+        assert_eq!(lens.get_capped(&value, 100), Err(CapExceeded { max: 100 }));
+        assert_eq!(lens.get_capped(&value, 1000).ok(), lens.get(&value));
+        assert_eq!(
+            Lens::new("a").get_capped(&value, 0),
+            Ok(View::BorrowVec(vec![]))
+        );
+
+        let value = json!({"payload": value.to_string()});
+        let embedded = Lens::new("payload").select(Lens::Embedded(Box::new(lens)));
+        assert_eq!(
+            embedded.get_capped(&value, 100),
+            Err(CapExceeded { max: 100 })
+        );
+        assert_eq!(embedded.get_capped(&value, 1000).ok(), embedded.get(&value));
+    }
+
+    #[test]
+    fn test_set_with_policy() {
+        let lens = Lens::new("items").select(5);
+        let set = |policy| {
+            let mut value = json!({"items": [1, 2]});
+            lens.set_with_policy(&mut value, json!(6), policy);
+            value["items"].clone()
+        };
+        assert_eq!(set(IndexPolicy::Ignore), json!([1, 2]));
+        assert_eq!(set(IndexPolicy::Append), json!([1, 2, 6]));
+        assert_eq!(set(IndexPolicy::Fill), json!([1, 2, null, null, null, 6]));
+
+        let mut value = json!({"items": [1, 2]});
+        let first = Lens::new("items").select(0);
+        first.set_with_policy(&mut value, json!(0), IndexPolicy::Append);
+        assert_eq!(value, json!({"items": [0, 2]}));
+        let huge = Lens::new("items").select(usize::MAX - 1);
+        huge.set_with_policy(&mut value, json!(0), IndexPolicy::Fill);
+        assert_eq!(value, json!({"items": [0, 2]}));
+    }
+
+    #[test]
+    fn test_set_and_format() {
+        let mut value = json!({"config": {"a": 1}});
+        let text = Lens::new("config")
+            .select("a")
+            .set_and_format(&mut value, json!([2]));
+        assert_eq!(
+            text,
+            "{\n  \"config\": {\n    \"a\": [\n      2\n    ]\n  }\n}"
+        );
+        assert_eq!(text, serde_json::to_string_pretty(&value).unwrap());
+    }
+
+    #[test]
+    fn test_set_through_null() {
+        let lens = Lens::new("a").select("b");
+        let mut value = json!({"a": null});
+        lens.set(&mut value, json!(1));
+        assert_eq!(value, json!({"a": null}));
+        lens.set_through_null(&mut value, json!(1));
+        assert_eq!(value, json!({"a": {"b": 1}}));
+
+        let mut value = json!({"items": [null, {"b": 2}, 3]});
+        Lens::new("items")
+            .each()
+            .select("b")
+            .set_through_null(&mut value, json!(0));
+        assert_eq!(value, json!({"items": [{"b": 0}, {"b": 0}, 3]}));
+
+        let mut value = json!({});
+        lens.set_through_null(&mut value, json!(1));
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_ensure() {
+        let mut value = json!({"a": {"b": 1}});
+        assert!(!Lens::new("a").select("b").ensure(&mut value, json!(0)));
+        assert_eq!(value, json!({"a": {"b": 1}}));
+
+        assert!(Lens::new("a").select("c").ensure(&mut value, json!(0)));
+        assert_eq!(value, json!({"a": {"b": 1, "c": 0}}));
+
+        let mut value = json!({"x": null});
+        assert!(Lens::new("x")
+            .select("y")
+            .select(1)
+            .select("z")
+            .ensure(&mut value, json!(true)));
+        assert_eq!(value, json!({"x": {"y": [null, {"z": true}]}}));
+
+        let mut value = json!({"items": [{"id": 1}, {"id": 2, "tags": []}]});
+        assert!(Lens::new("items")
+            .each()
+            .select("tags")
+            .ensure(&mut value, json!([])));
+        assert_eq!(
+            value,
+            json!({"items": [{"id": 1, "tags": []}, {"id": 2, "tags": []}]})
+        );
+
+        let mut value = json!([]);
+        assert!(!Lens::new(usize::MAX - 1).ensure(&mut value, json!(0)));
+        assert_eq!(value, json!([]));
+
+        let nested = Lens::new("a").select(usize::MAX - 1);
+        let mut value = json!({});
+        assert!(!nested.ensure(&mut value, json!(0)));
+        assert_eq!(value, json!({}));
+        let mut value = Value::Null;
+        assert!(!Lens::new(0).select(nested).ensure(&mut value, json!(0)));
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn test_indices() {
+        let mut value = json!([10, 20, 30]);
+        assert_eq!(
+            Lens::indices(&[0, 2]).get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!(10)),
+                View::Borrow(&json!(30))
+            ]))
+        );
+
+        let lens = Lens::indices(&[2, 0, 7]);
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!(30)),
+                View::Borrow(&json!(10))
+            ]))
+        );
+
+        lens.set(&mut value, json!(0));
+        assert_eq!(value, json!([0, 20, 0]));
+    }
+
+    #[test]
+    fn test_set_if_changed() {
+        let mut value = json!({"a": [{"b": 1}, {"b": 2}]});
+        let lens = Lens::new("a").each().select("b");
+
+        assert!(lens.set_if_changed(&mut value, json!(2)));
+        assert_eq!(value, json!({"a": [{"b": 2}, {"b": 2}]}));
+        assert!(!lens.set_if_changed(&mut value, json!(2)));
+
+        let lens = Lens::new("c");
+        assert!(lens.set_if_changed(&mut value, json!(1)));
+        assert!(!lens.set_if_changed(&mut value, json!(1)));
+        assert!(!Lens::new("x")
+            .select("y")
+            .set_if_changed(&mut value, json!(1)));
+    }
+
+    #[test]
+    fn test_set_if_changed_partial() {
+        let lens = Lens::foreach().select("a");
+        let mut value = json!([{"a": 1}, {}]);
+        assert!(lens.set_if_changed(&mut value, json!(1)));
+        assert_eq!(value, json!([{"a": 1}, {"a": 1}]));
+        assert!(!lens.set_if_changed(&mut value, json!(1)));
+
+        let mut expected = json!([{"a": 1}, {}, 3]);
+        lens.set(&mut expected, json!(2));
+        let mut value = json!([{"a": 1}, {}, 3]);
+        assert!(lens.set_if_changed(&mut value, json!(2)));
+        assert_eq!(value, expected);
+
+        let embedded = Lens::new("s").select(Lens::Embedded(Box::new(Lens::new("x"))));
+        let mut value = json!({"s": "{ \"x\": 1 }"});
+        assert!(!embedded.set_if_changed(&mut value, json!(1)));
+        assert_eq!(value, json!({"s": "{ \"x\": 1 }"}));
+        assert!(embedded.set_if_changed(&mut value, json!(2)));
+        assert_eq!(value, json!({"s": "{\"x\":2}"}));
+    }
+
+    #[test]
+    fn test_walk() {
+        let value = json!({"users": [{"name": "a"}, {"id": 1}, {"name": "c"}]});
+        let mut leaves = Vec::new();
+        Lens::new("users")
+            .each()
+            .select("name")
+            .walk(&value, |path, value| {
+                leaves.push((path.to_vec(), value.clone()))
+            });
+        assert_eq!(
+            leaves,
+            vec![
+                (
+                    vec![
+                        Segment::Field("users".to_string()),
+                        Segment::Index(0),
+                        Segment::Field("name".to_string())
+                    ],
+                    json!("a")
+                ),
+                (
+                    vec![
+                        Segment::Field("users".to_string()),
+                        Segment::Index(2),
+                        Segment::Field("name".to_string())
+                    ],
+                    json!("c")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cast() {
+        let lens = Lens::Cast(Box::new(Lens::new("age")), JsonType::Number);
+        let mut value = json!({"age": 1});
+        lens.set(&mut value, json!("42"));
+        assert_eq!(value, json!({"age": 42}));
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!(42))));
+
+        let value = json!({"age": " 7 "});
+        assert_eq!(lens.get(&value), Some(View::Owned(json!(7))));
+
+        let lens = Lens::Cast(Box::new(Lens::foreach()), JsonType::String);
+        let value = json!([1, true, null]);
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Owned(json!("1")),
+                View::Owned(json!("true"))
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_cast_uncoercible() {
+        let lens = Lens::Cast(Box::new(Lens::new("age")), JsonType::Number);
+        let mut value = json!({"age": "old"});
+        assert_eq!(lens.get(&value), None);
+        assert_eq!(
+            lens.try_get(&value).unwrap_err(),
+            LensError {
+                path: "cast(age, number)".to_string(),
+                kind: LensErrorKind::TypeMismatch {
+                    expected: "number",
+                    found: "string"
+                }
+            }
+        );
+
+        lens.set(&mut value, json!("forty"));
+        assert_eq!(value, json!({"age": "old"}));
+    }
+
+    #[test]
+    fn test_get_into() {
+        let value = json!({"a": [{"b": 1}, {"c": 2}, {"b": 3}], "s": "{}"});
+        let lens = Lens::new("a").each().select("b");
+        let mut buf = vec![&value];
+        assert!(lens.get_into(&value, &mut buf));
+        assert_eq!(buf, vec![&json!(1), &json!(3)]);
+
+        let missing = Lens::new("x");
+        assert!(!missing.get_into(&value, &mut buf));
+        assert!(buf.is_empty());
+
+        let embedded = Lens::new("s").select(Lens::Embedded(Box::default()));
+        assert!(!embedded.get_into(&value, &mut buf));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_rename_keys() {
+        let mapping = HashMap::from([("a".to_string(), "x".to_string())]);
+        let mut value = json!({"a": 1, "b": 2});
+        Lens::default().rename_keys(&mut value, &mapping);
+        assert_eq!(value, json!({"x": 1, "b": 2}));
+
+        let mapping = HashMap::from([
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "a".to_string()),
+        ]);
+        let mut value = json!([{"a": 1, "b": 2}, {"a": 3}, 4]);
+        Lens::foreach().rename_keys(&mut value, &mapping);
+        assert_eq!(value, json!([{"a": 2, "b": 1}, {"b": 3}, 4]));
+    }
+
+    fn guard() -> Lens {
+        Lens::Guard {
+            condition: Box::new(Lens::new("enabled")),
+            expected: json!(true),
+            inner: Box::new(Lens::new("payload")),
+        }
+    }
+
+    #[test]
+    fn test_guard() {
+        let mut value = json!({"enabled": true, "payload": 1});
+        assert_eq!(guard().get(&value), Some(View::Borrow(&json!(1))));
+
+        guard().set(&mut value, json!(2));
+        assert_eq!(value, json!({"enabled": true, "payload": 2}));
+
+        let mut value = json!([{"enabled": true, "payload": 1}, {"payload": 2}]);
+        Lens::foreach().select(guard()).set(&mut value, json!(0));
+        assert_eq!(
+            value,
+            json!([{"enabled": true, "payload": 0}, {"payload": 2}])
+        );
+    }
+
+    #[test]
+    fn test_guard_owned_condition() {
+        let lens = Lens::Guard {
+            condition: Box::new(Lens::Cast(Box::new(Lens::new("version")), JsonType::Number)),
+            expected: json!(1),
+            inner: Box::new(Lens::new("payload")),
+        };
+        let value = json!({"version": "1", "payload": "v1"});
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!("v1"))));
+        assert_eq!(lens.get(&json!({"version": "2", "payload": "v2"})), None);
+    }
+
+    #[test]
+    fn test_guard_failed() {
+        let mut value = json!({"enabled": false, "payload": 1});
+        assert_eq!(guard().get(&value), None);
+        assert_eq!(
+            guard().try_get(&value).unwrap_err(),
+            LensError {
+                path: "guard(enabled == true, payload)".to_string(),
+                kind: LensErrorKind::GuardFailed
+            }
+        );
+
+        guard().set(&mut value, json!(2));
+        guard().delete(&mut value);
+        assert_eq!(value, json!({"enabled": false, "payload": 1}));
+    }
+
+    #[test]
+    fn test_set_observed() {
+        let mut value = json!({"users": [{"name": "a"}, {"name": "b"}, {}]});
+        let lens = Lens::new("users").each().select("name");
+        let changes = lens.set_observed(&mut value, json!("b"));
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    path: "users[0].name".to_string(),
+                    old: Some(json!("a")),
+                    new: json!("b")
+                },
+                Change {
+                    path: "users[2].name".to_string(),
+                    old: None,
+                    new: json!("b")
+                },
+            ]
+        );
+        assert!(lens.set_observed(&mut value, json!("b")).is_empty());
+    }
+
+    #[test]
+    fn test_find_key() {
+        let value = json!({"jobs": {"a": {"status": "x"}, "b": {"status": "active"}}});
+        let active = |value: &Value| value["status"] == "active";
+        assert_eq!(
+            Lens::new("jobs").find_key(&value, active),
+            Some("b".to_string())
+        );
+        assert_eq!(Lens::new("jobs").select("a").find_key(&value, active), None);
+        assert_eq!(Lens::new("missing").find_key(&value, active), None);
+    }
+
+    #[cfg(feature = "raw_value")]
+    #[test]
+    fn test_get_raw() {
+        let value = json!({"a": {"b": {"c": [1, 2]}}});
+        let raw = Lens::new("a").select("b").get_raw(&value).unwrap();
+        assert_eq!(raw.get(), r#"{"c":[1,2]}"#);
+
+        let document = HashMap::from([("copy", raw)]);
+        assert_eq!(
+            serde_json::to_string(&document).unwrap(),
+            r#"{"copy":{"c":[1,2]}}"#
+        );
+
+        assert!(Lens::new("a").select("x").get_raw(&value).is_none());
+        assert!(Lens::new("a")
+            .select("b")
+            .select("c")
+            .each()
+            .get_raw(&value)
+            .is_none());
+    }
+
+    #[test]
+    fn test_exists() {
+        let value = json!({"a": null, "c": []});
+        assert!(Lens::new("a").exists(&value));
+        assert!(Lens::new("c").each().exists(&value));
+        assert!(!Lens::new("b").exists(&value));
+
+        let lenses = [Lens::new("a"), Lens::new("b")];
+        assert!(Lens::any_exist(&lenses, &value));
+        assert!(!Lens::all_exist(&lenses, &value));
+        assert!(Lens::all_exist(&[], &value));
+    }
+
+    #[test]
+    fn test_truncate_str() {
+        let mut value = json!({"note": "hello"});
+        Lens::new("note").truncate_str(&mut value, 3);
+        assert_eq!(value, json!({"note": "hel"}));
+
+        let mut value = json!(["héllo", "hi", 42]);
+        Lens::foreach().truncate_str(&mut value, 2);
+        assert_eq!(value, json!(["hé", "hi", 42]));
+    }
+
+    #[test]
+    fn test_map_strings() {
+        let mut value =
+            json!({"users": [{"name": "Ada"}, {"name": 7}, {"id": 1}, {"name": "BOB"}]});
+        Lens::new("users")
+            .each()
+            .select("name")
+            .map_strings(&mut value, str::to_lowercase);
+        assert_eq!(
+            value,
+            json!({"users": [{"name": "ada"}, {"name": 7}, {"id": 1}, {"name": "bob"}]})
+        );
+    }
+
+    #[test]
+    fn test_get_indexed() {
+        let value = json!({"users": [{"name": "a"}, {"id": 1}, {"name": "c"}]});
+        let lens = Lens::new("users").each().select("name");
+        assert_eq!(
+            lens.get_indexed(&value),
+            vec![(0, &json!("a")), (2, &json!("c"))]
+        );
+        assert!(Lens::new("users").get_indexed(&value).is_empty());
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        let mut value = json!({"items": [{"label": "a"}, {"label": "b"}, {"id": 3}]});
+        let mut paths = Vec::new();
+        Lens::new("items")
+            .each()
+            .select("label")
+            .walk_mut(&mut value, |path, label| {
+                if let (Some(Segment::Index(index)), Value::String(text)) = (path.get(1), label) {
+                    text.push_str(&index.to_string());
+                }
+                paths.push(Lens::from_segments(path).to_string());
+            });
+        assert_eq!(
+            value,
+            json!({"items": [{"label": "a0"}, {"label": "b1"}, {"id": 3}]})
+        );
+        assert_eq!(paths, vec!["items[0].label", "items[1].label"]);
+    }
+
+    #[test]
+    fn test_over_indexed() {
+        let mut value = json!({"items": [{"id": "a"}, {"id": "b"}], "grid": [[0, 0], [0]]});
+        Lens::new("items")
+            .each()
+            .over_indexed(&mut value, |index, item| item["seq"] = json!(index));
+        assert_eq!(
+            value["items"],
+            json!([{"id": "a", "seq": 0}, {"id": "b", "seq": 1}])
+        );
+
+        Lens::new("grid")
+            .each()
+            .each()
+            .over_indexed(&mut value, |index, cell| *cell = json!(index));
+        assert_eq!(value["grid"], json!([[0, 1], [0]]));
+    }
+
+    #[test]
+    fn test_field_literal() {
+        let value = json!({"a.b": 1, "a": {"b": 2}});
+        let literal = Lens::field_literal("a.b");
+        assert_eq!(literal.get(&value), Some(View::Borrow(&json!(1))));
+        assert_eq!(
+            Lens::parse("a.b").unwrap().get(&value),
+            Some(View::Borrow(&json!(2)))
+        );
+        assert_eq!(Lens::parse(&literal.to_string()), Ok(literal));
+    }
+
+    #[test]
+    fn test_get_each_aligned() {
+        let value = json!([{"a": 1}, {}, {"a": 3}]);
+        assert_eq!(
+            Lens::foreach().select("a").get_each_aligned(&value),
+            vec![Some(&json!(1)), None, Some(&json!(3))]
+        );
+
+        let value = json!({"rows": [[1, 2], [3], []]});
+        let lens = Lens::new("rows").each().select(1);
+        assert_eq!(
+            lens.get_each_aligned(&value),
+            vec![Some(&json!(2)), None, None]
+        );
+        assert!(Lens::new("rows").get_each_aligned(&value).is_empty());
+    }
+
+    #[test]
+    fn test_eq_at() {
+        let a = json!({"id": 1, "tags": ["x", "y"]});
+        let b = json!({"id": 2, "tags": ["x", "y"]});
+        assert!(Lens::new("tags").each().eq_at(&a, &b));
+        assert!(!Lens::new("id").eq_at(&a, &b));
+        assert!(!Lens::new("missing").eq_at(&a, &a));
+
+        let c = json!({"tags": ["x"]});
+        assert!(!Lens::new("tags").each().eq_at(&a, &c));
+    }
+
+    #[test]
+    fn test_nested_for_each_set() {
+        let mut value = json!([[{"a": 1}, {"a": 2}], [{"a": 3}]]);
+        let lens = Lens::foreach().each().select("a");
+        lens.set(&mut value, json!(0));
+        assert_eq!(value, json!([[{"a": 0}, {"a": 0}], [{"a": 0}]]));
+
+        let mut value = json!([[[{"a": 1}], [{"a": 2}, {"a": 3}]], [[{"a": 4}]]]);
+        let lens = Lens::foreach().each().each().select("a");
+        lens.set(&mut value, json!(0));
+        assert_eq!(
+            value,
+            json!([[[{"a": 0}], [{"a": 0}, {"a": 0}]], [[{"a": 0}]]])
+        );
+        assert_eq!(lens.get_all_mut(&mut value).len(), 4);
+    }
+
+    #[test]
+    fn test_get_string() {
+        let value = json!({"name": "Jane", "age": 42});
+        assert_eq!(
+            Lens::new("name").get_string(&value),
+            Some("Jane".to_string())
+        );
+        assert_eq!(Lens::new("age").get_string(&value), None);
+        assert_eq!(Lens::new("missing").get_string(&value), None);
+    }
+
+    #[test]
+    fn test_focus_kind() {
+        let value = json!({"name": "a", "user": {"id": 1}, "tags": ["x", "y"]});
+        assert_eq!(
+            Lens::new("name").focus_kind(&value),
+            Some(FocusKind::Scalar)
+        );
+        assert_eq!(
+            Lens::new("user").focus_kind(&value),
+            Some(FocusKind::Object)
+        );
+        assert_eq!(Lens::new("tags").focus_kind(&value), Some(FocusKind::Array));
+        assert_eq!(
+            Lens::new("tags").each().focus_kind(&value),
+            Some(FocusKind::Multi)
+        );
+        assert_eq!(Lens::new("missing").focus_kind(&value), None);
+    }
+
+    #[test]
+    fn test_get_non_null() {
+        let value = json!({"a": null, "items": [{"b": 1}, {"b": null}, {}]});
+        assert_eq!(Lens::new("a").get(&value), Some(View::Borrow(&Value::Null)));
+        assert_eq!(Lens::new("a").get_non_null(&value), None);
+        assert_eq!(Lens::new("missing").get_non_null(&value), None);
+        assert_eq!(
+            Lens::new("items").each().select("b").get_non_null(&value),
+            Some(View::BorrowVec(vec![View::Borrow(&json!(1))]))
+        );
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut value = json!({"count": 1, "ratio": 0.5, "name": "x"});
+        assert!(Lens::new("count").increment(&mut value, 2.0));
+        assert!(Lens::new("ratio").increment(&mut value, 0.25));
+        assert!(!Lens::new("name").increment(&mut value, 1.0));
+        assert_eq!(value, json!({"count": 3, "ratio": 0.75, "name": "x"}));
+        assert!(value["count"].is_i64());
+
+        let mut value = json!([1, "a", 2]);
+        assert!(Lens::foreach().increment(&mut value, 0.5));
+        assert_eq!(value, json!([1.5, "a", 2.5]));
     }
 
     #[test]
-    fn test_flatten_structure() {
-        let mut value = json!({
-            "a": {
-                "b": {
-                    "c": [1, 2, {"d": 3}]
-                }
+    fn test_increment_precision() {
+        let mut value: Value = serde_json::from_str(r#"{"id": 9007199254740993}"#).unwrap();
+        let lens = Lens::new("id");
+        lens.set(&mut value, json!(9007199254740993u64));
+        assert_eq!(value.to_string(), r#"{"id":9007199254740993}"#);
+        assert!(lens.increment(&mut value, 2.0));
+        assert_eq!(value.to_string(), r#"{"id":9007199254740995}"#);
+
+        let mut value = json!({"id": u64::MAX - 1});
+        assert!(lens.increment(&mut value, 1.0));
+        assert_eq!(value, json!({"id": u64::MAX}));
+        assert!(lens.increment(&mut value, -3.0));
+        assert_eq!(value, json!({"id": u64::MAX - 3}));
+
+        let mut value = json!({"id": -9007199254740993i64});
+        assert!(lens.increment(&mut value, -1.0));
+        assert_eq!(value, json!({"id": -9007199254740994i64}));
+    }
+
+    #[test]
+    fn test_push() {
+        let mut value = json!({"log": []});
+        assert!(Lens::new("log").push(&mut value, json!("started")));
+        assert_eq!(value, json!({"log": ["started"]}));
+        assert!(!Lens::new("missing").push(&mut value, json!(1)));
+
+        let mut value = json!([[1], {"a": 1}, []]);
+        assert!(Lens::foreach().push(&mut value, json!(0)));
+        assert_eq!(value, json!([[1, 0], {"a": 1}, [0]]));
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut value = json!({"stack": [1, 2, 3]});
+        assert_eq!(Lens::new("stack").pop(&mut value), Some(json!(3)));
+        assert_eq!(value, json!({"stack": [1, 2]}));
+
+        let mut value = json!({"stack": [], "name": "x", "all": [[1]]});
+        assert_eq!(Lens::new("stack").pop(&mut value), None);
+        assert_eq!(Lens::new("name").pop(&mut value), None);
+        assert_eq!(Lens::new("all").each().pop(&mut value), None);
+        assert_eq!(value["all"], json!([[1]]));
+    }
+
+    #[test]
+    fn test_field_ci() {
+        let lens = Lens::FieldCI("Name".to_string());
+        let mut value = json!({"name": "a"});
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!("a"))));
+        assert_eq!(lens.resolve(&value), vec![Lens::new("name")]);
+
+        lens.set(&mut value, json!("b"));
+        assert_eq!(value, json!({"name": "b"}));
+
+        let mut value = json!({});
+        lens.set(&mut value, json!("c"));
+        assert_eq!(value, json!({"Name": "c"}));
+
+        lens.delete(&mut value);
+        assert_eq!(value, json!({}));
+        assert_eq!(
+            lens.try_get(&value).unwrap_err().to_string(),
+            "missing field `Name` at `ci(Name)`"
+        );
+    }
+
+    #[test]
+    fn test_unflatten() {
+        let rules = [("meta".to_string(), vec!["x".to_string(), "y".to_string()])];
+        let value = Lens::default().unflatten(&json!({"x": 1}), &rules);
+        assert_eq!(value, json!({"meta": {"x": 1}}));
+        assert_eq!(
+            Lens::new("meta").select("x").get(&value),
+            Some(View::Borrow(&json!(1)))
+        );
+
+        let value = json!([{"id": 1, "y": 2, "meta": {"z": 3}}, {"id": 2}]);
+        assert_eq!(
+            Lens::foreach().unflatten(&value, &rules),
+            json!([{"id": 1, "meta": {"y": 2, "z": 3}}, {"id": 2}])
+        );
+
+        let value = json!({"x": 1, "meta": "v1"});
+        assert_eq!(Lens::default().unflatten(&value, &rules), value);
+    }
+
+    #[test]
+    fn test_merge_each() {
+        let mut value = json!({"items": [{"a": 1}, {"a": 2}]});
+        let lens = Lens::new("items");
+        lens.merge_each(&mut value, vec![json!({"b": 9}), json!({})]);
+        assert_eq!(value, json!({"items": [{"a": 1, "b": 9}, {"a": 2}]}));
+
+        lens.merge_each(
+            &mut value,
+            vec![json!({"a": null}), json!({}), json!({"c": 1})],
+        );
+        assert_eq!(value, json!({"items": [{"b": 9}, {"a": 2}]}));
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let mut value = json!({"a": 9});
+        Lens::Empty.apply_defaults(&mut value, &json!({"a": 1, "b": 2}));
+        assert_eq!(value, json!({"a": 9, "b": 2}));
+
+        let mut value = json!({"config": {"db": {"host": "x", "port": null}, "tags": [1]}});
+        let template =
+            json!({"db": {"host": "localhost", "port": 5432, "pool": 4}, "tags": [2, 3]});
+        Lens::new("config").apply_defaults(&mut value, &template);
+        assert_eq!(
+            value,
+            json!({"config": {"db": {"host": "x", "port": null, "pool": 4}, "tags": [1]}})
+        );
+    }
+
+    #[test]
+    fn test_last_index() {
+        let mut value = json!({"a": [1, 2, 3], "b": []});
+        let last = Lens::new("a").select(Lens::LAST);
+        assert_eq!(last.get(&value), Some(View::Borrow(&json!(3))));
+        assert_eq!(last.resolve(&value), vec![Lens::new("a").select(2)]);
+
+        last.set(&mut value, json!(4));
+        assert_eq!(value["a"], json!([1, 2, 4]));
+        last.delete(&mut value);
+        assert_eq!(value["a"], json!([1, 2]));
+
+        let empty = Lens::new("b").select(Lens::LAST);
+        assert_eq!(empty.get(&value), None);
+        empty.set(&mut value, json!(1));
+        assert!(!empty.ensure(&mut value, json!(1)));
+        assert_eq!(value["b"], json!([]));
+
+        let large = Lens::new("a").select(Lens::LAST - 1);
+        assert_eq!(
+            large.try_get(&value).unwrap_err().kind,
+            LensErrorKind::IndexOutOfBounds {
+                index: Lens::LAST - 1,
+                len: 2
             }
+        );
+    }
+
+    #[test]
+    fn test_partition() {
+        let value = json!({"numbers": [1, 2, 3, 4, 5], "name": "x"});
+        let even = |value: &Value| value.as_i64().is_some_and(|n| n % 2 == 0);
+        assert_eq!(
+            Lens::new("numbers").partition(&value, even),
+            (vec![json!(2), json!(4)], vec![json!(1), json!(3), json!(5)])
+        );
+        assert_eq!(
+            Lens::new("name").partition(&value, even),
+            (Vec::new(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_union_order() {
+        let value = json!({"id": 1, "tags": ["x", "y"], "name": "n"});
+        let lens = Lens::Union(vec![
+            Lens::new("name"),
+            Lens::new("tags").each(),
+            Lens::new("missing"),
+            Lens::new("id"),
+        ]);
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!("n")),
+                View::Borrow(&json!("x")),
+                View::Borrow(&json!("y")),
+                View::Borrow(&json!(1)),
+            ]))
+        );
+        assert_eq!(
+            Lens::fields(&["name", "id"]).get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!("n")),
+                View::Borrow(&json!(1))
+            ]))
+        );
+
+        let value = json!({"grid": [[1, 2], [3]], "id": 4});
+        let lens = Lens::Union(vec![Lens::new("grid").each().each(), Lens::new("id")]);
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!(1)),
+                View::Borrow(&json!(2)),
+                View::Borrow(&json!(3)),
+                View::Borrow(&json!(4)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut value = json!([1, 2, 3]);
+        assert!(Lens::default().swap(&mut value, 0, 2));
+        assert_eq!(value, json!([3, 2, 1]));
+        assert!(!Lens::default().swap(&mut value, 0, 3));
+        assert_eq!(value, json!([3, 2, 1]));
+
+        let mut value = json!([[1, 2], [3]]);
+        assert!(Lens::foreach().swap(&mut value, 0, 1));
+        assert_eq!(value, json!([[2, 1], [3]]));
+    }
+
+    #[test]
+    fn test_adjacent() {
+        let lens = Lens::adjacent("t", "A", "c").select("x");
+        let mut value = json!({"t": "A", "c": {"x": 1}});
+        assert_eq!(lens.get(&value), Some(View::Borrow(&json!(1))));
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"t": "A", "c": {"x": 2}}));
+
+        let mut value = json!({"t": "B", "c": {"x": 1}});
+        assert_eq!(lens.get(&value), None);
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"t": "B", "c": {"x": 1}}));
+    }
+
+    #[test]
+    fn test_modify() {
+        let mut value = json!({"hits": 1});
+        let old = Lens::new("hits").modify(&mut value, |hits| {
+            let old = hits.as_i64().unwrap();
+            *hits = json!(old + 1);
+            old
         });
+        assert_eq!(old, Some(1));
+        assert_eq!(value, json!({"hits": 2}));
 
-        let lens = Lens::default().select("a").select("b").select("c").each();
-        let view = lens.get(&value).unwrap();
+        assert_eq!(Lens::new("missing").modify(&mut value, |_| ()), None);
+        let mut value = json!([1, 2]);
+        assert_eq!(Lens::foreach().modify(&mut value, |_| ()), None);
+    }
+
+    #[test]
+    fn test_descend_limited() {
+        let lens = |max_depth| Lens::DescendLimited {
+            key: "id".to_string(),
+            max_depth,
+        };
+        let mut value = json!({"a": {"c": [{"id": 1}], "id": 2}, "id": 3});
         assert_eq!(
-            view,
-            View::BorrowVec(vec![
+            lens(4).get(&value),
+            Some(View::BorrowVec(vec![
                 View::Borrow(&json!(1)),
                 View::Borrow(&json!(2)),
-                View::Borrow(&json!({"d": 3}))
+                View::Borrow(&json!(3))
+            ]))
+        );
+        assert_eq!(
+            lens(2).resolve(&value),
+            vec![Lens::new("a").select("id"), Lens::new("id")]
+        );
+
+        lens(2).set(&mut value, json!(0));
+        assert_eq!(value, json!({"a": {"c": [{"id": 1}], "id": 0}, "id": 0}));
+        lens(4).delete(&mut value);
+        assert_eq!(value, json!({"a": {"c": [{}]}}));
+    }
+
+    #[test]
+    fn test_descend_limited_deep() {
+        let value = (0..1_000).fold(json!({"id": 0}), |value, _| {
+            Value::Object(Map::from_iter([("a".to_string(), value)]))
+        });
+        let lens = Lens::DescendLimited {
+            key: "id".to_string(),
+            max_depth: 8,
+        };
+        assert_eq!(lens.get(&value), Some(View::BorrowVec(Vec::new())));
+    }
+
+    #[test]
+    fn test_select_elides_empty() {
+        let field = Lens::Field("a".to_string());
+        assert_eq!(Lens::Empty.select("a"), field);
+        assert_eq!(Lens::new("a").select(Lens::Empty), field);
+        assert_eq!(Lens::Empty.select(Lens::Empty), Lens::Empty);
+
+        let lens = Lens::Compose(
+            Box::new(Lens::Compose(
+                Box::new(Lens::Empty),
+                Box::new(Lens::new("a")),
+            )),
+            Box::new(Lens::Union(vec![Lens::Compose(
+                Box::new(Lens::new("b")),
+                Box::new(Lens::Empty),
+            )])),
+        );
+        assert_eq!(
+            lens.normalize(),
+            Lens::new("a").select(Lens::Union(vec![Lens::new("b")]))
+        );
+    }
+
+    #[test]
+    fn test_set_empty() {
+        let mut value = json!({"a": 1});
+        Lens::Empty.set(&mut value, json!(42));
+        assert_eq!(value, json!(42));
+
+        let mut value = json!({"a": {"b": 1}});
+        let lens = Lens::Compose(Box::new(Lens::new("a")), Box::new(Lens::Empty));
+        lens.set(&mut value, json!(2));
+        assert_eq!(value, json!({"a": 2}));
+        assert_eq!(lens.normalize(), Lens::new("a"));
+        assert!(Lens::default().set_if_changed(&mut value, json!([])));
+        assert_eq!(value, json!([]));
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let mut value = json!({"a": 1, "b": 2});
+        assert_eq!(
+            Lens::Wildcard.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!(1)),
+                View::Borrow(&json!(2))
+            ]))
+        );
+        if let Some(Modify::BorrowVec(values)) = Lens::Wildcard.get_mut(&mut value) {
+            for value in values {
+                value.set(&Lens::Empty, json!(0));
+            }
+        }
+        assert_eq!(value, json!({"a": 0, "b": 0}));
+
+        let mut value = json!({"a": {"n": 1}, "b": {"n": 2}});
+        let lens = Lens::new(Lens::Wildcard).select("n");
+        lens.set(&mut value, json!(3));
+        assert_eq!(value, json!({"a": {"n": 3}, "b": {"n": 3}}));
+        assert_eq!(
+            lens.resolve(&value),
+            vec![Lens::new("a").select("n"), Lens::new("b").select("n")]
+        );
+        assert_eq!(lens.to_string(), "*.n");
+        assert_eq!(Lens::Wildcard.get(&json!([1])), None);
+    }
+
+    #[test]
+    fn test_glob() {
+        let mut value = json!({"user_1": "a", "user_2": "b", "admin": "c"});
+        let lens = Lens::Glob("user_*".to_string());
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!("a")),
+                View::Borrow(&json!("b"))
+            ]))
+        );
+        lens.set(&mut value, json!("x"));
+        assert_eq!(value, json!({"user_1": "x", "user_2": "x", "admin": "c"}));
+        lens.delete(&mut value);
+        assert_eq!(value, json!({"admin": "c"}));
+        assert_eq!(lens.to_string(), "glob(\"user_*\")");
+
+        assert!(glob_match("user_?", "user_1"));
+        assert!(!glob_match("user_?", "user_10"));
+        assert!(glob_match("*_*_?", "a_b_c"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("a*b", "ac"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+    }
+
+    #[test]
+    fn test_lens_is_static() {
+        fn assert_static<T: 'static>(_: &T) {}
+
+        let key = String::from("a");
+        let lens = Lens::new(key.as_str()).each();
+        drop(key);
+        assert_static(&lens);
+
+        let get = move |value: &Value| lens.get(value).map(View::into_value);
+        assert_static(&get);
+        assert_eq!(get(&json!({"a": [1]})), Some(json!([1])));
+    }
+
+    #[test]
+    fn test_length() {
+        let mut value = json!({"items": [1, 2, 3]});
+        let lens = Lens::parse("items.#").unwrap();
+        assert_eq!(lens, Lens::new("items").select(Lens::Length));
+        assert_eq!(lens.get(&value), Some(View::Owned(json!(3))));
+
+        lens.set(&mut value, json!(1));
+        assert_eq!(value, json!({"items": [1]}));
+        lens.set(&mut value, json!(3));
+        assert_eq!(value, json!({"items": [1, null, null]}));
+        assert!(lens.try_set(&mut value, json!(-1)).is_err());
+        assert!(lens.try_set(&mut value, json!(u64::MAX)).is_err());
+        assert!(lens.try_delete(&mut value).is_err());
+        assert_eq!(value, json!({"items": [1, null, null]}));
+        assert_eq!(
+            Lens::new("items")
+                .each()
+                .select(Lens::Length)
+                .get(&json!({"items": [[1], []]})),
+            Some(View::BorrowVec(vec![
+                View::Owned(json!(1)),
+                View::Owned(json!(0))
+            ]))
+        );
+        assert_eq!(Lens::Length.get(&json!({})), None);
+    }
+
+    #[test]
+    fn test_map() {
+        let uppercase = |value: &Value| match value {
+            Value::String(text) => Value::String(text.to_uppercase()),
+            value => value.clone(),
+        };
+        let mut value = json!({"name": "jane", "tags": ["a", "b"]});
+        let lens = Lens::new("name").map(uppercase);
+        assert_eq!(lens.get(&value), Some(View::Owned(json!("JANE"))));
+        assert_eq!(
+            Lens::new("tags").each().map(uppercase).get(&value),
+            Some(View::BorrowVec(vec![
+                View::Owned(json!("A")),
+                View::Owned(json!("B"))
+            ]))
+        );
+
+        lens.set(&mut value, json!("john"));
+        assert_eq!(value["name"], json!("john"));
+        assert!(lens.try_get_mut(&mut value).is_err());
+        assert_eq!(lens.to_string(), "map(name)");
+        assert_eq!(lens.clone(), lens);
+        assert!(serde_json::to_string(&lens).is_err());
+    }
+
+    #[test]
+    fn test_by_id() {
+        let mut value = json!({"items": [{"id": "a", "n": 1}, {"id": "b", "n": 2}]});
+        let by_id = |id: &str| Lens::ById {
+            field: "id".to_string(),
+            value: json!(id),
+        };
+        let lens = Lens::new("items").select(by_id("b"));
+        assert_eq!(
+            lens.get(&value),
+            Some(View::Borrow(&json!({"id": "b", "n": 2})))
+        );
+        assert_eq!(lens.to_string(), "items.by_id(id == \"b\")");
+        assert_eq!(lens.resolve(&value), vec![Lens::new("items").select(1)]);
+
+        lens.clone().select("n").set(&mut value, json!(3));
+        if let Some(Modify::BorrowMut(element)) = lens.get_mut(&mut value) {
+            element["seen"] = json!(true);
+        }
+        assert_eq!(value["items"][1], json!({"id": "b", "n": 3, "seen": true}));
+
+        let missing = Lens::new("items").select(by_id("c"));
+        assert_eq!(missing.get(&value), None);
+        assert_eq!(
+            missing.try_set(&mut value, json!(0)).unwrap_err().kind,
+            LensErrorKind::NoMatch
+        );
+        lens.delete(&mut value);
+        assert_eq!(value, json!({"items": [{"id": "a", "n": 1}]}));
+    }
+
+    #[test]
+    fn test_serialize_focus() {
+        let value =
+            json!({"config": {"name": "lens", "tags": ["a", "b"]}, "items": [{"n": 1}, {"n": 2}]});
+
+        let mut out = Vec::new();
+        Lens::new("config")
+            .serialize_focus(&value, &mut out)
+            .unwrap();
+        assert_eq!(out, serde_json::to_vec(&value["config"]).unwrap());
+
+        let mut out = Vec::new();
+        let lens = Lens::new("items").each().select("n");
+        lens.serialize_focus(&value, &mut out).unwrap();
+        assert_eq!(out, b"[1,2]");
+
+        let mut out = Vec::new();
+        let err = Lens::new("missing")
+            .serialize_focus(&value, &mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_set_from() {
+        #[derive(Serialize)]
+        struct Owner {
+            name: &'static str,
+            admin: bool,
+        }
+
+        let mut value = json!({"repo": {"meta": {}}, "tags": [{}, {}]});
+        let owner = Owner {
+            name: "ada",
+            admin: true,
+        };
+        Lens::new("repo")
+            .select("meta")
+            .select("owner")
+            .set_from(&mut value, &owner);
+        assert_eq!(
+            value["repo"],
+            json!({"meta": {"owner": {"name": "ada", "admin": true}}})
+        );
+
+        Lens::new("tags").each().set_from(&mut value, &owner.name);
+        assert_eq!(value["tags"], json!(["ada", "ada"]));
+
+        let invalid = HashMap::from([((1, 2), 3)]);
+        Lens::new("tags").set_from(&mut value, &invalid);
+        assert_eq!(value["tags"], json!(["ada", "ada"]));
+    }
+
+    #[test]
+    fn test_set_interpolated() {
+        let mut value = json!({"paths": {}});
+        let vars = HashMap::from([("HOME".to_string(), "/home/lens".to_string())]);
+        let lens = Lens::new("paths").select("data");
+        lens.set_interpolated(&mut value, "${HOME}/x", &vars);
+        assert_eq!(value, json!({"paths": {"data": "/home/lens/x"}}));
+
+        lens.set_interpolated(&mut value, "${HOME}:${USER}:${HOME", &vars);
+        assert_eq!(value["paths"]["data"], json!("/home/lens:${USER}:${HOME"));
+    }
+
+    #[test]
+    fn test_owned_focus_keeps_null_leaves() {
+        let value = json!([{"a": null}, {"b": 2}, {"a": 1}]);
+        let lens = Lens::foreach().select("a");
+        assert_eq!(lens.get_window_owned(&value), Some(json!([null, 1])));
+        assert_eq!(
+            lens.get(&value).map(View::into_value),
+            Some(json!([null, 1]))
+        );
+        assert_eq!(
+            lens.get_owned_view(&value),
+            Some(OwnedView::Vec(vec![
+                OwnedView::Value(Value::Null),
+                OwnedView::Value(json!(1))
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_get_window_owned() {
+        let value = json!({"items": [10, 20, 30, 40]});
+        let skip = Lens::new("items").select(Lens::Skip(1));
+        assert_eq!(skip.get_window_owned(&value), Some(json!([20, 30, 40])));
+        let take = Lens::new("items").select(Lens::Take(2));
+        assert_eq!(take.get_window_owned(&value), Some(json!([10, 20])));
+        let middle = Lens::new("items").select(Lens::indices(&[1, 2]));
+        assert_eq!(middle.get_window_owned(&value), Some(json!([20, 30])));
+
+        assert_eq!(
+            Lens::new("items").select(0).get_window_owned(&value),
+            Some(json!([10]))
+        );
+        assert_eq!(Lens::new("missing").get_window_owned(&value), None);
+    }
+
+    #[test]
+    fn test_get_as() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Server {
+            host: String,
+            port: u16,
+        }
+
+        let value = json!({"server": {"host": "localhost", "port": 8080}, "name": "x"});
+        assert_eq!(
+            Lens::new("server").get_as(&value),
+            Some(Server {
+                host: "localhost".to_string(),
+                port: 8080
+            })
+        );
+        assert_eq!(Lens::new("name").get_as::<Server>(&value), None);
+        assert_eq!(
+            Lens::new("server").select("port").get_as(&value),
+            Some(8080u16)
+        );
+        let port = Lens::Cast(
+            Box::new(Lens::new("server").select("port")),
+            JsonType::String,
+        );
+        assert_eq!(port.get_as(&value), Some("8080".to_string()));
+    }
+
+    #[test]
+    fn test_get_opt_as() {
+        let value = json!({"port": 8080, "host": null});
+        assert_eq!(Lens::new("port").get_opt_as(&value), Some(Some(8080u16)));
+        assert_eq!(Lens::new("host").get_opt_as::<String>(&value), Some(None));
+        assert_eq!(Lens::new("user").get_opt_as::<String>(&value), None);
+        assert_eq!(Lens::new("port").get_opt_as::<String>(&value), None);
+    }
+
+    #[test]
+    fn test_get_object_and_array() {
+        let value = json!({"config": {"a": 1}, "items": [1, 2], "name": "x"});
+        let lens = Lens::new("config");
+        let config = lens.get_object(&value).unwrap();
+        assert_eq!(
+            config.iter().collect::<Vec<_>>(),
+            vec![(&"a".to_string(), &json!(1))]
+        );
+        assert_eq!(
+            Lens::new("items").get_array(&value),
+            Some(&vec![json!(1), json!(2)])
+        );
+
+        assert_eq!(Lens::new("config").get_array(&value), None);
+        assert_eq!(Lens::new("items").get_object(&value), None);
+        assert_eq!(Lens::new("name").get_object(&value), None);
+        assert_eq!(Lens::new("name").get_array(&value), None);
+    }
+
+    #[test]
+    fn test_array_diff() {
+        let lens = Lens::new("items");
+        let old = json!({"items": [1, 2, 3]});
+        let new = json!({"items": [1, 9, 3, 4]});
+        assert_eq!(
+            lens.array_diff(&old, &new),
+            Some(vec![
+                ArrayChange::Changed(1, json!(2), json!(9)),
+                ArrayChange::Added(3, json!(4)),
+            ])
+        );
+        assert_eq!(
+            lens.array_diff(&new, &old),
+            Some(vec![
+                ArrayChange::Changed(1, json!(9), json!(2)),
+                ArrayChange::Removed(3, json!(4)),
             ])
         );
+        assert_eq!(lens.array_diff(&old, &old), Some(vec![]));
+        assert_eq!(lens.array_diff(&old, &json!({"items": {}})), None);
+    }
 
-        lens.set(&mut value, json!(4));
+    #[test]
+    fn test_get_typed() {
+        let value = json!({"o": {}, "a": [1], "s": "x", "n": 1.5, "b": true, "z": null});
+        for (key, name) in [
+            ("o", "object"),
+            ("a", "array"),
+            ("s", "string"),
+            ("n", "number"),
+            ("b", "bool"),
+            ("z", "null"),
+        ] {
+            assert_eq!(Lens::new(key).get_typed(&value), Some((name, &value[key])));
+        }
+        assert_eq!(Lens::new("a").each().get_typed(&value), None);
+        assert_eq!(Lens::new("missing").get_typed(&value), None);
+    }
+
+    #[test]
+    fn test_first() {
+        let value = json!({"users": [{"id": 1}, {"name": "b"}, {"name": "c"}]});
+        let names = Lens::new("users").each().select("name");
+        assert_eq!(names.first(&value), Some(&json!("b")));
+        assert_eq!(
+            Lens::new("users").each().first(&value),
+            Some(&value["users"][0])
+        );
+        assert_eq!(Lens::new("users").select(5).first(&value), None);
+    }
+
+    #[test]
+    fn test_contains() {
+        let value =
+            json!({"users": [{"role": "user"}, {"role": "admin"}], "meta": {"a": 1, "b": "2"}});
+        let roles = Lens::new("users").each().select("role");
+        assert!(roles.contains(&value, &json!("admin")));
+        assert!(!roles.contains(&value, &json!("owner")));
+
+        assert!(Lens::new("meta")
+            .select(Lens::Wildcard)
+            .contains(&value, &json!(1)));
+        assert!(!Lens::new("meta")
+            .select(Lens::Wildcard)
+            .contains(&value, &json!(2)));
+        let cast = Lens::Cast(Box::new(Lens::new("meta").select("b")), JsonType::Number);
+        assert!(cast.contains(&value, &json!(2)));
+        assert!(!Lens::new("missing").contains(&value, &Value::Null));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_get_datetime() {
+        use chrono::Datelike;
+
+        let value = json!({"created": "2024-03-01T23:30:00-02:00", "updated": "yesterday"});
+        let created = Lens::new("created").get_datetime(&value).unwrap();
+        assert_eq!(created.to_rfc3339(), "2024-03-02T01:30:00+00:00");
+        assert_eq!(created.year(), 2024);
+        assert_eq!(Lens::new("updated").get_datetime(&value), None);
+        assert_eq!(Lens::new("missing").get_datetime(&value), None);
+    }
+
+    #[test]
+    fn test_by_ids() {
+        let mut value = json!([{"id": "a"}, {"id": "b"}, {"id": "c"}]);
+        let lens = Lens::ByIds {
+            field: "id".to_string(),
+            values: vec![json!("c"), json!("x"), json!("a"), json!("c")],
+        };
+        assert_eq!(
+            lens.get(&value),
+            Some(View::BorrowVec(vec![
+                View::Borrow(&json!({"id": "c"})),
+                View::Borrow(&json!({"id": "a"}))
+            ]))
+        );
+        assert_eq!(lens.to_string(), "by_ids(id in [\"c\",\"x\",\"a\",\"c\"])");
+        assert_eq!(lens.resolve(&value), vec![Lens::new(2), Lens::new(0)]);
+
+        lens.clone().select("seen").set(&mut value, json!(true));
         assert_eq!(
             value,
-            json!({
-                "a": {
-                    "b": {
-                        "c": [4, 4, 4]
-                    }
-                }
-            })
+            json!([{"id": "a", "seen": true}, {"id": "b"}, {"id": "c", "seen": true}])
         );
+        lens.delete(&mut value);
+        assert_eq!(value, json!([{"id": "b"}]));
     }
 }
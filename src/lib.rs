@@ -1,9 +1,43 @@
+mod cache;
+mod cast;
+mod codec;
+mod compiled;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+mod data;
+mod error;
 mod lens;
+mod matches;
 mod modify;
+mod op;
+mod parse;
+mod patch;
+mod pointer;
+#[cfg(feature = "jsonschema")]
+mod schema;
+mod segment;
 mod select;
+#[cfg(feature = "toml")]
+mod toml;
 mod view;
+#[cfg(feature = "yaml")]
+mod yaml;
 
+#[cfg(feature = "toml")]
+pub use self::toml::*;
+pub use cache::*;
+pub use cast::*;
+pub use compiled::*;
+pub use error::*;
 pub use lens::*;
 pub use modify::*;
+pub use op::*;
+pub use parse::*;
+pub use patch::*;
+pub use pointer::*;
+#[cfg(feature = "jsonschema")]
+pub use schema::*;
+pub use segment::*;
 pub use select::*;
 pub use view::*;
+#[cfg(feature = "yaml")]
+pub use yaml::*;
@@ -0,0 +1,139 @@
+use std::ops::ControlFlow;
+
+use serde_json::Value;
+
+use crate::lens::glob_match;
+use crate::{Lens, Segment};
+
+type Children<'a> = Box<dyn Iterator<Item = (Vec<Segment>, &'a Value)> + 'a>;
+
+impl Lens {
+    /// Lazily yields the concrete lens and value of every leaf the lens
+    /// focuses, in the order of [`Lens::resolve`]. Arrays iterated by a
+    /// `ForEach`, `Take` or `Skip`, and objects iterated by a `Wildcard` or a
+    /// `Glob`, are only walked as far as the iterator is advanced, so
+    /// stopping early skips the rest of the document. Other segments are
+    /// expanded one step at a time.
+    pub fn iter_matches<'a>(&'a self, value: &'a Value) -> impl Iterator<Item = (Lens, &'a Value)> {
+        Matches::new(self, value)
+    }
+}
+
+/// A depth-first walk over the segments of a lens, with one frame of pending
+/// children per segment entered so far.
+struct Matches<'a> {
+    parts: Vec<&'a Lens>,
+    path: Vec<Segment>,
+    /// The index of the part each frame's children are matched against next,
+    /// the length of `path` above the frame, and the children themselves.
+    stack: Vec<(usize, usize, Children<'a>)>,
+}
+
+impl<'a> Matches<'a> {
+    fn new(lens: &'a Lens, value: &'a Value) -> Self {
+        Matches {
+            parts: lens.parts(),
+            path: Vec::new(),
+            stack: vec![(0, 0, Box::new(std::iter::once((Vec::new(), value))))],
+        }
+    }
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = (Lens, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (part, len, children) = self.stack.last_mut()?;
+            let (part, len) = (*part, *len);
+            let Some((segments, value)) = children.next() else {
+                self.stack.pop();
+                continue;
+            };
+            self.path.truncate(len);
+            self.path.extend(segments);
+            match self.parts.get(part) {
+                Some(lens) => {
+                    let children = children_of(lens, value);
+                    self.stack.push((part + 1, self.path.len(), children));
+                }
+                None => return Some((Lens::from_segments(&self.path), value)),
+            }
+        }
+    }
+}
+
+fn children_of<'a>(lens: &'a Lens, value: &'a Value) -> Children<'a> {
+    let elements = value.as_array().into_iter().flatten().enumerate();
+    let element = |(index, value)| (vec![Segment::Index(index)], value);
+    match lens {
+        Lens::ForEach => Box::new(elements.map(element)),
+        Lens::Take(n) => Box::new(elements.take(*n).map(element)),
+        Lens::Skip(n) => Box::new(elements.skip(*n).map(element)),
+        Lens::Wildcard => Box::new(
+            value
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| (vec![Segment::Field(key.clone())], value)),
+        ),
+        Lens::Glob(pattern) => Box::new(
+            value
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(key, _)| glob_match(pattern, key))
+                .map(|(key, value)| (vec![Segment::Field(key.clone())], value)),
+        ),
+        lens => {
+            let mut children = Vec::new();
+            let _ = lens.traverse(value, &mut Vec::new(), &mut |path, value| {
+                children.push((path.clone(), value));
+                ControlFlow::Continue(())
+            });
+            Box::new(children.into_iter())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_iter_matches() {
+        let value = json!({"items": [{"a": 1}, {"b": 2}, {"a": 3}]});
+        let lens = Lens::new("items").each().select("a");
+        let matches: Vec<_> = lens.iter_matches(&value).collect();
+        assert_eq!(
+            matches,
+            vec![
+                (Lens::new("items").select(0).select("a"), &json!(1)),
+                (Lens::new("items").select(2).select("a"), &json!(3)),
+            ]
+        );
+        let resolved: Vec<_> = matches.into_iter().map(|(lens, _)| lens).collect();
+        assert_eq!(resolved, lens.resolve(&value));
+        assert_eq!(
+            Lens::Empty.iter_matches(&value).next(),
+            Some((Lens::Empty, &value))
+        );
+    }
+
+    #[test]
+    fn test_iter_matches_take() {
+        let value = json!({"items": (0..1000).map(|n| json!({"a": n})).collect::<Vec<_>>()});
+        let lens = Lens::new("items").each().select("a");
+        let mut matches = Matches::new(&lens, &value);
+        assert_eq!(
+            matches.by_ref().take(1).collect::<Vec<_>>(),
+            vec![(Lens::new("items").select(0).select("a"), &json!(0))]
+        );
+
+        // The frame iterating the array has only handed out its first element.
+        let (_, _, elements) = &matches.stack[2];
+        assert_eq!(elements.size_hint(), (999, Some(999)));
+    }
+}
@@ -1,6 +1,6 @@
 use serde_json::Value;
 
-use crate::Lens;
+use crate::{Lens, LensError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Modify<'a> {
@@ -10,9 +10,13 @@ pub enum Modify<'a> {
 
 impl<'a> Modify<'a> {
     pub fn get_mut(self, lens: &'a Lens) -> Option<Self> {
+        self.try_get_mut(lens).ok()
+    }
+
+    pub fn try_get_mut(self, lens: &'a Lens) -> Result<Self, LensError> {
         match self {
-            Modify::BorrowMut(value) => lens.get_mut(value),
-            Modify::BorrowVec(vec) => Some(Modify::BorrowVec(
+            Modify::BorrowMut(value) => lens.try_get_mut(value),
+            Modify::BorrowVec(vec) => Ok(Modify::BorrowVec(
                 vec.into_iter()
                     .filter_map(|value| value.get_mut(lens))
                     .collect(),
@@ -21,15 +25,75 @@ impl<'a> Modify<'a> {
     }
 
     pub fn set(self, lens: &Lens, new_value: Value) {
+        let _ = self.try_set(lens, new_value);
+    }
+
+    pub fn try_set(self, lens: &Lens, new_value: Value) -> Result<(), LensError> {
+        self.write(lens, new_value, &mut None)
+    }
+
+    /// Like `try_set`, tracking writes as [`Lens::write`] does.
+    pub(crate) fn write(
+        self,
+        lens: &Lens,
+        new_value: Value,
+        changed: &mut Option<&mut bool>,
+    ) -> Result<(), LensError> {
         match self {
-            Modify::BorrowMut(value) => {
-                lens.set(value, new_value);
-            }
-            Modify::BorrowVec(values) => {
+            Modify::BorrowMut(value) => lens.write(value, new_value, changed),
+            Modify::BorrowVec(values) => first_error(
                 values
                     .into_iter()
-                    .for_each(|value| value.set(lens, new_value.clone()));
+                    .map(|value| value.write(lens, new_value.clone(), changed)),
+            ),
+        }
+    }
+
+    pub fn try_delete(self, lens: &Lens) -> Result<(), LensError> {
+        match self {
+            Modify::BorrowMut(value) => lens.try_delete(value),
+            Modify::BorrowVec(values) => {
+                first_error(values.into_iter().map(|value| value.try_delete(lens)))
             }
         }
     }
+
+    pub fn try_merge(self, lens: &Lens, patch: Value) -> Result<(), LensError> {
+        match self {
+            Modify::BorrowMut(value) => lens.try_merge(value, patch),
+            Modify::BorrowVec(values) => first_error(
+                values
+                    .into_iter()
+                    .map(|value| value.try_merge(lens, patch.clone())),
+            ),
+        }
+    }
+
+    /// Flattens the focus into its leaves. The references are disjoint since
+    /// every leaf comes from a distinct array or object slot.
+    pub fn into_vec(self) -> Vec<&'a mut Value> {
+        match self {
+            Modify::BorrowMut(value) => vec![value],
+            Modify::BorrowVec(values) => values.into_iter().flat_map(Modify::into_vec).collect(),
+        }
+    }
+
+    pub(crate) fn for_each<F: FnMut(&mut Value)>(self, f: &mut F) {
+        match self {
+            Modify::BorrowMut(value) => f(value),
+            Modify::BorrowVec(values) => values.into_iter().for_each(|value| value.for_each(f)),
+        }
+    }
+}
+
+/// Runs every result to completion, so each element of a multi-focus is
+/// still written, and returns the first error among them.
+fn first_error<I: Iterator<Item = Result<(), LensError>>>(results: I) -> Result<(), LensError> {
+    let mut first = Ok(());
+    for result in results {
+        if first.is_ok() {
+            first = result;
+        }
+    }
+    first
 }
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::lens::add_number;
+use crate::{JsonType, Lens, LensError, LensErrorKind, Modify};
+
+/// A single edit applied relative to the focus of a [`Lens::transaction`].
+#[derive(Debug)]
+pub enum LensOp {
+    Set(Lens, Value),
+    Delete(Lens),
+    Merge(Lens, Value),
+}
+
+impl LensOp {
+    pub fn apply(self, modify: Modify<'_>) -> Result<(), LensError> {
+        match self {
+            LensOp::Set(lens, value) => modify.try_set(&lens, value),
+            LensOp::Delete(lens) => modify.try_delete(&lens),
+            LensOp::Merge(lens, patch) => modify.try_merge(&lens, patch),
+        }
+    }
+}
+
+/// An operation applied at the focus of a lens by [`Lens::apply`]. Unlike
+/// [`LensOp`] it carries no lens of its own and can be serialized, paired
+/// with its lens as an [`Edit`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Operation {
+    Get,
+    Set(Value),
+    Delete,
+    Over(Transform),
+    Merge(Value),
+}
+
+/// A leaf-wise update of an [`Operation::Over`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Transform {
+    /// Adds to a number, as [`Lens::increment`] does.
+    Increment(f64),
+    /// Coerces a scalar, as [`Lens::Cast`] does on read.
+    Cast(JsonType),
+}
+
+impl Transform {
+    pub(crate) fn apply(&self, leaf: &mut Value) -> Result<(), LensErrorKind> {
+        match self {
+            Transform::Increment(by) => match leaf {
+                Value::Number(number) => {
+                    *number = add_number(number, *by)
+                        .ok_or(LensErrorKind::Unsupported("non-finite number"))?;
+                }
+                leaf => return Err(LensErrorKind::mismatch("number", leaf)),
+            },
+            Transform::Cast(ty) => *leaf = ty.coerce(leaf)?,
+        }
+        Ok(())
+    }
+}
+
+/// The result of [`Lens::apply`]: the focus for [`Operation::Get`], `None`
+/// for the edits.
+pub type OpResult = Result<Option<Value>, LensError>;
+
+/// A lens paired with the operation to apply at its focus, e.g. for sending
+/// a whole edit over the wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edit {
+    pub lens: Lens,
+    pub op: Operation,
+}
+
+impl Edit {
+    pub fn apply(self, source: &mut Value) -> OpResult {
+        self.lens.apply(source, self.op)
+    }
+}
+
+/// A leaf written by [`Lens::set_observed`], with its concrete path such as
+/// `users[2].name` and its value before the write, `None` if it was created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Value,
+}
+
+/// A difference between two arrays found by [`Lens::array_diff`], with the
+/// index it occurs at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayChange {
+    Added(usize, Value),
+    Removed(usize, Value),
+    /// The old and the new element.
+    Changed(usize, Value, Value),
+}
+
+/// Deep-merges `patch` into `target` following JSON Merge Patch (RFC 7396):
+/// objects are merged key by key, `null` removes a key and anything else
+/// replaces the target.
+pub(crate) fn merge_patch(target: &mut Value, patch: Value) {
+    match patch {
+        Value::Object(patch) => {
+            if !target.is_object() {
+                *target = Value::Object(Default::default());
+            }
+            if let Some(obj) = target.as_object_mut() {
+                for (key, value) in patch {
+                    if value.is_null() {
+                        obj.remove(&key);
+                    } else {
+                        merge_patch(obj.entry(key).or_insert(Value::Null), value);
+                    }
+                }
+            }
+        }
+        patch => *target = patch,
+    }
+}
+
+/// Inserts every entry of `template` missing from `target`, recursing into
+/// objects present in both. Existing values, `null` included, are kept and
+/// arrays are never merged.
+pub(crate) fn fill_defaults(target: &mut Value, template: &Value) {
+    let (Value::Object(target), Value::Object(template)) = (target, template) else {
+        return;
+    };
+    for (key, default) in template {
+        match target.get_mut(key) {
+            Some(value) => fill_defaults(value, default),
+            None => {
+                target.insert(key.clone(), default.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Lens;
+
+/// Failure to parse a lens path, pointing at the byte offset in the input
+/// where parsing stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Lens {
+    /// Parses a dotted path such as `a.b[0][*]["c.d"]`, the syntax `Lens`
+    /// is displayed in. `[n]` selects an index, `[*]` every element, `[:n]`
+    /// and `[n:]` take or skip the first `n` elements, `*` every value of an
+    /// object, `#` the length of an array and `["..."]` selects a field whose
+    /// name is a JSON string, so it may contain dots, brackets or be `*`.
+    /// A leading `$` denotes the document root, as in JSONPath, and an empty
+    /// path or a bare `$` yields `Lens::Empty`.
+    pub fn parse(input: &str) -> Result<Lens, ParseError> {
+        Parser { input, position: 0 }.parse()
+    }
+}
+
+impl FromStr for Lens {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Lens::parse(input)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Lens, ParseError> {
+        let mut lens = Lens::Empty;
+        if self.rest().starts_with('$')
+            && matches!(self.rest()[1..].chars().next(), None | Some('.' | '['))
+        {
+            self.position += 1;
+            if self.rest().starts_with('.') {
+                self.position += 1;
+                lens = lens.select(self.field()?);
+            }
+        } else if !self.rest().is_empty() && !self.rest().starts_with('[') {
+            lens = lens.select(self.field()?);
+        }
+
+        while let Some(c) = self.rest().chars().next() {
+            lens = match c {
+                '.' => {
+                    self.position += 1;
+                    lens.select(self.field()?)
+                }
+                '[' => lens.select(self.bracket()?),
+                c => {
+                    return Err(ParseError::new(
+                        self.position,
+                        format!("unexpected `{}`", c),
+                    ))
+                }
+            };
+        }
+        Ok(lens)
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    fn field(&mut self) -> Result<Lens, ParseError> {
+        let len = self
+            .rest()
+            .find(['.', '[', ']'])
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(ParseError::new(self.position, "expected a field name"));
+        }
+        let field = self.rest()[..len].to_string();
+        self.position += len;
+        Ok(match field.as_str() {
+            "*" => Lens::Wildcard,
+            "#" => Lens::Length,
+            _ => Lens::Field(field),
+        })
+    }
+
+    fn bracket(&mut self) -> Result<Lens, ParseError> {
+        let start = self.position;
+        self.position += 1;
+        let lens = if self.rest().starts_with('"') {
+            let mut stream = serde_json::Deserializer::from_str(self.rest()).into_iter::<String>();
+            match stream.next() {
+                Some(Ok(field)) => {
+                    self.position += stream.byte_offset();
+                    Lens::Field(field)
+                }
+                _ => return Err(ParseError::new(self.position, "invalid quoted field")),
+            }
+        } else if self.rest().starts_with('*') {
+            self.position += 1;
+            Lens::ForEach
+        } else {
+            let len = self.rest().find(']').unwrap_or(self.rest().len());
+            let inner = &self.rest()[..len];
+            let invalid = |_| {
+                ParseError::new(
+                    self.position,
+                    "expected an index, a window, `*` or a quoted field",
+                )
+            };
+            let lens = if let Some(n) = inner.strip_prefix(':') {
+                Lens::Take(n.parse().map_err(invalid)?)
+            } else if let Some(n) = inner.strip_suffix(':') {
+                Lens::Skip(n.parse().map_err(invalid)?)
+            } else {
+                Lens::Index(inner.parse().map_err(invalid)?)
+            };
+            self.position += len;
+            lens
+        };
+        if !self.rest().starts_with(']') {
+            return Err(ParseError::new(start, "unclosed `[`"));
+        }
+        self.position += 1;
+        Ok(lens)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Lens::parse(""), Ok(Lens::Empty));
+        assert_eq!(Lens::parse("a"), Ok(Lens::new("a")));
+        assert_eq!(
+            Lens::parse("a.b[0][*]"),
+            Ok(Lens::new("a").select("b").select(0).each())
+        );
+        assert_eq!(
+            Lens::parse("a[\"b.c\"].d"),
+            Ok(Lens::new("a").select("b.c").select("d"))
+        );
+        assert_eq!(Lens::parse("[1]"), Ok(Lens::new(1)));
+    }
+
+    #[test]
+    fn test_parse_root() {
+        assert_eq!(Lens::parse("$"), Ok(Lens::Empty));
+        assert_eq!(Lens::parse("$.a"), Ok(Lens::new("a")));
+        assert_eq!(Lens::parse("$.a[0]"), Ok(Lens::new("a").select(0)));
+        assert_eq!(Lens::parse("$[0]"), Ok(Lens::new(0)));
+        assert_eq!(Lens::parse("$a"), Ok(Lens::new("$a")));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Lens::parse("a..b").unwrap_err().position, 2);
+        assert_eq!(Lens::parse("a[x]").unwrap_err().position, 2);
+        assert_eq!(Lens::parse("a[0").unwrap_err().position, 1);
+        assert!(Lens::parse("a]").is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let lens = Lens::new("a").select("b.c").select(0).each().select("d");
+        assert_eq!(Lens::parse(&lens.to_string()), Ok(lens));
+
+        let lens = Lens::new("a").select(Lens::Take(2)).select(Lens::Skip(1));
+        assert_eq!(lens.to_string(), "a[:2][1:]");
+        assert_eq!(Lens::parse(&lens.to_string()), Ok(lens));
+
+        for key in ["a.b", "[0]", "a\"]", "", "$", "\\", "ü"] {
+            let lens = Lens::field_literal(key);
+            assert_eq!(Lens::parse(&lens.to_string()), Ok(lens));
+        }
+
+        for key in ["*", "#"] {
+            let lens = Lens::field_literal(key);
+            assert_eq!(Lens::parse(&lens.to_string()), Ok(lens));
+        }
+
+        let lens = Lens::new("a").select(Lens::Wildcard).select("*");
+        assert_eq!(lens.to_string(), "a.*[\"*\"]");
+        assert_eq!(Lens::parse(&lens.to_string()), Ok(lens));
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::pointer::{index, tokens};
+use crate::{Lens, Modify, PointerError, View};
+
+/// A failed [`Lens::apply_json_patch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The patch isn't an array of well-formed operations.
+    Invalid(String),
+    Pointer(PointerError),
+    /// Nothing exists at the given path, or it can't hold a new value.
+    NotFound(String),
+    /// A `test` operation found a different value at the given path.
+    TestFailed(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Invalid(message) => write!(f, "invalid patch: {}", message),
+            PatchError::Pointer(err) => write!(f, "invalid pointer: {}", err),
+            PatchError::NotFound(path) => write!(f, "nothing found at `{}`", path),
+            PatchError::TestFailed(path) => write!(f, "test failed at `{}`", path),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl From<PointerError> for PatchError {
+    fn from(err: PointerError) -> Self {
+        PatchError::Pointer(err)
+    }
+}
+
+impl Lens {
+    /// Applies a JSON Patch (RFC 6902) to every focus, with the `path` and
+    /// `from` pointers of each operation relative to the focus. The patch
+    /// runs against a copy of `source`, which is only written back once every
+    /// operation succeeded, so a failing operation or `test` leaves `source`
+    /// untouched.
+    pub fn apply_json_patch(&self, source: &mut Value, patch: &Value) -> Result<(), PatchError> {
+        let ops = patch
+            .as_array()
+            .ok_or_else(|| PatchError::Invalid("expected an array of operations".to_string()))?;
+        let mut working = source.clone();
+        let leaves = self.get_all_mut(&mut working);
+        if leaves.is_empty() {
+            return Err(PatchError::NotFound(self.to_string()));
+        }
+        for leaf in leaves {
+            for op in ops {
+                apply(leaf, op)?;
+            }
+        }
+        *source = working;
+        Ok(())
+    }
+}
+
+fn apply(document: &mut Value, op: &Value) -> Result<(), PatchError> {
+    let name = op
+        .get("op")
+        .and_then(Value::as_str)
+        .ok_or_else(|| PatchError::Invalid("missing `op`".to_string()))?;
+    let (path, to) = pointer(op, "path")?;
+    match name {
+        "add" => add(document, path, &to, operand(op)?.clone()),
+        "remove" => remove(document, path, &to).map(drop),
+        "replace" => {
+            remove(document, path, &to)?;
+            add(document, path, &to, operand(op)?.clone())
+        }
+        "move" => {
+            let (from_path, from) = pointer(op, "from")?;
+            if to.len() > from.len() && to.starts_with(&from) {
+                return Err(PatchError::Invalid(format!(
+                    "cannot move `{}` into itself",
+                    from_path
+                )));
+            }
+            let value = remove(document, from_path, &from)?;
+            add(document, path, &to, value)
+        }
+        "copy" => {
+            let (from_path, from) = pointer(op, "from")?;
+            let value = get(document, from_path, &from)?;
+            add(document, path, &to, value)
+        }
+        "test" => {
+            if get(document, path, &to)? == *operand(op)? {
+                Ok(())
+            } else {
+                Err(PatchError::TestFailed(path.to_string()))
+            }
+        }
+        name => Err(PatchError::Invalid(format!("unknown op `{}`", name))),
+    }
+}
+
+fn pointer<'a>(op: &'a Value, key: &str) -> Result<(&'a str, Vec<String>), PatchError> {
+    let pointer = op
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| PatchError::Invalid(format!("missing `{}`", key)))?;
+    Ok((pointer, tokens(pointer)?))
+}
+
+fn operand(op: &Value) -> Result<&Value, PatchError> {
+    op.get("value")
+        .ok_or_else(|| PatchError::Invalid("missing `value`".to_string()))
+}
+
+/// Builds the lens a pointer denotes in `document`, reading each token as an
+/// index or a key depending on the container it's applied to. Returns `None`
+/// if any token doesn't resolve.
+fn locate(document: &Value, tokens: &[String]) -> Option<Lens> {
+    let mut lens = Lens::Empty;
+    let mut value = document;
+    for token in tokens {
+        (lens, value) = match value {
+            Value::Array(arr) => {
+                let index = index(token)?;
+                (lens.select(index), arr.get(index)?)
+            }
+            Value::Object(obj) => (lens.select(token.as_str()), obj.get(token)?),
+            _ => return None,
+        };
+    }
+    Some(lens)
+}
+
+fn get(document: &Value, path: &str, tokens: &[String]) -> Result<Value, PatchError> {
+    locate(document, tokens)
+        .and_then(|lens| lens.get(document).map(View::into_value))
+        .ok_or_else(|| PatchError::NotFound(path.to_string()))
+}
+
+/// The lens focusing the container that holds the last token of a pointer.
+fn parent(document: &Value, path: &str, tokens: &[String]) -> Result<Lens, PatchError> {
+    locate(document, &tokens[..tokens.len() - 1])
+        .ok_or_else(|| PatchError::NotFound(path.to_string()))
+}
+
+fn add(
+    document: &mut Value,
+    path: &str,
+    tokens: &[String],
+    value: Value,
+) -> Result<(), PatchError> {
+    let Some(last) = tokens.last() else {
+        *document = value;
+        return Ok(());
+    };
+    let parent = parent(document, path, tokens)?;
+    match parent.get_mut(document) {
+        Some(Modify::BorrowMut(Value::Object(obj))) => {
+            obj.insert(last.clone(), value);
+            Ok(())
+        }
+        Some(Modify::BorrowMut(Value::Array(arr))) if last == "-" => {
+            arr.push(value);
+            Ok(())
+        }
+        Some(Modify::BorrowMut(Value::Array(arr))) => match index(last) {
+            Some(index) if index <= arr.len() => {
+                arr.insert(index, value);
+                Ok(())
+            }
+            _ => Err(PatchError::NotFound(path.to_string())),
+        },
+        _ => Err(PatchError::NotFound(path.to_string())),
+    }
+}
+
+fn remove(document: &mut Value, path: &str, tokens: &[String]) -> Result<Value, PatchError> {
+    let Some(last) = tokens.last() else {
+        return Ok(std::mem::take(document));
+    };
+    let parent = parent(document, path, tokens)?;
+    let removed = match parent.get_mut(document) {
+        Some(Modify::BorrowMut(Value::Object(obj))) => obj.remove(last),
+        Some(Modify::BorrowMut(Value::Array(arr))) => match index(last) {
+            Some(index) if index < arr.len() => Some(arr.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+    removed.ok_or_else(|| PatchError::NotFound(path.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn patch(ops: Value) -> Result<Value, PatchError> {
+        let mut value = json!({"config": {"a": 1, "list": [1, 2], "nested": {"b": 2}}});
+        Lens::new("config")
+            .apply_json_patch(&mut value, &ops)
+            .map(|()| value)
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        assert_eq!(
+            patch(json!([
+                {"op": "add", "path": "/c", "value": 3},
+                {"op": "add", "path": "/list/1", "value": 9},
+                {"op": "add", "path": "/list/-", "value": 4},
+                {"op": "remove", "path": "/a"},
+            ])),
+            Ok(json!({"config": {"c": 3, "list": [1, 9, 2, 4], "nested": {"b": 2}}}))
+        );
+    }
+
+    #[test]
+    fn test_replace_move_and_copy() {
+        assert_eq!(
+            patch(json!([
+                {"op": "replace", "path": "/list/0", "value": 0},
+                {"op": "move", "from": "/nested/b", "path": "/b"},
+                {"op": "copy", "from": "/a", "path": "/nested/a"},
+            ])),
+            Ok(json!({"config": {"a": 1, "b": 2, "list": [0, 2], "nested": {"a": 1}}}))
+        );
+    }
+
+    #[test]
+    fn test_test() {
+        assert!(patch(json!([{"op": "test", "path": "/nested/b", "value": 2}])).is_ok());
+        assert_eq!(
+            patch(json!([
+                {"op": "remove", "path": "/a"},
+                {"op": "test", "path": "/nested/b", "value": 3},
+            ])),
+            Err(PatchError::TestFailed("/nested/b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            patch(json!([{"op": "replace", "path": "/missing", "value": 0}])),
+            Err(PatchError::NotFound("/missing".to_string()))
+        );
+        assert_eq!(
+            patch(json!([{"op": "add", "path": "/list/5", "value": 0}])),
+            Err(PatchError::NotFound("/list/5".to_string()))
+        );
+        assert!(matches!(
+            patch(json!([{"op": "move", "from": "/nested", "path": "/nested/x"}])),
+            Err(PatchError::Invalid(_))
+        ));
+        assert!(matches!(
+            patch(json!([{"op": "add", "path": "x", "value": 0}])),
+            Err(PatchError::Pointer(_))
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::Lens;
+
+/// Failure to parse a JSON Pointer (RFC 6901), pointing at the byte offset in
+/// the input where parsing stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+impl Lens {
+    /// Parses a JSON Pointer such as `/a/0/b~1c`. A pointer doesn't say
+    /// whether a token names a key or an index, so tokens made of digits
+    /// without a leading zero become `Index` segments and everything else a
+    /// `Field`. The empty pointer yields `Lens::Empty`.
+    pub fn from_pointer(pointer: &str) -> Result<Lens, PointerError> {
+        Lens::Empty.select_pointer(pointer)
+    }
+
+    /// Extends the lens with the segments of a JSON Pointer, read as by
+    /// [`Lens::from_pointer`].
+    pub fn select_pointer(self, pointer: &str) -> Result<Lens, PointerError> {
+        Ok(tokens(pointer)?
+            .into_iter()
+            .fold(self, |lens, token| match index(&token) {
+                Some(index) => lens.select(index),
+                None => lens.select(Lens::Field(token)),
+            }))
+    }
+}
+
+/// Splits a pointer into its unescaped reference tokens.
+pub(crate) fn tokens(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(PointerError {
+            position: 0,
+            message: "expected `/`".to_string(),
+        });
+    }
+
+    let mut tokens = Vec::new();
+    let mut position = 1;
+    for raw in pointer[1..].split('/') {
+        let mut token = String::with_capacity(raw.len());
+        let mut chars = raw.char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '~' => match chars.next() {
+                    Some((_, '0')) => token.push('~'),
+                    Some((_, '1')) => token.push('/'),
+                    _ => {
+                        return Err(PointerError {
+                            position: position + offset,
+                            message: "expected `~0` or `~1`".to_string(),
+                        })
+                    }
+                },
+                c => token.push(c),
+            }
+        }
+        position += raw.len() + 1;
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Parses an array index token, which has no sign and no leading zeros.
+pub(crate) fn index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_pointer() {
+        assert_eq!(Lens::from_pointer(""), Ok(Lens::Empty));
+        assert_eq!(
+            Lens::from_pointer("/a/0/b~1c/~0d/01"),
+            Ok(Lens::new("a")
+                .select(0)
+                .select("b/c")
+                .select("~d")
+                .select("01"))
+        );
+        assert_eq!(Lens::from_pointer("/"), Ok(Lens::new("")));
+    }
+
+    #[test]
+    fn test_select_pointer() {
+        let lens = Lens::new("data").select_pointer("/items/0/name");
+        assert_eq!(
+            lens,
+            Ok(Lens::new("data").select("items").select(0).select("name"))
+        );
+        assert_eq!(Lens::new("data").select_pointer(""), Ok(Lens::new("data")));
+        assert!(Lens::new("data").select_pointer("items").is_err());
+    }
+
+    #[test]
+    fn test_from_pointer_errors() {
+        assert_eq!(Lens::from_pointer("a").unwrap_err().position, 0);
+        assert_eq!(Lens::from_pointer("/ab/c~2").unwrap_err().position, 5);
+    }
+}
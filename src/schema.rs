@@ -0,0 +1,106 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::Lens;
+
+/// A failed [`Lens::set_validated`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The schema itself couldn't be compiled.
+    Schema(String),
+    /// The updated document violates the schema, one message per violation.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Schema(message) => write!(f, "invalid schema: {}", message),
+            ValidationError::Invalid(errors) => {
+                write!(f, "schema validation failed: {}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Lens {
+    /// Like [`Lens::set`], but only writes the result back to `source` if the
+    /// whole updated document validates against `schema`; otherwise `source`
+    /// is left untouched and every violation is returned.
+    pub fn set_validated(
+        &self,
+        source: &mut Value,
+        target: Value,
+        schema: &Value,
+    ) -> Result<(), ValidationError> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|err| ValidationError::Schema(err.to_string()))?;
+        let mut working = source.clone();
+        self.set(&mut working, target);
+        let errors: Vec<String> = validator
+            .iter_errors(&working)
+            .map(|err| format!("{} at `{}`", err, err.instance_path()))
+            .collect();
+        if !errors.is_empty() {
+            return Err(ValidationError::Invalid(errors));
+        }
+        *source = working;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "port": {"type": "integer", "minimum": 1}
+            }
+        })
+    }
+
+    #[test]
+    fn test_set_validated() {
+        let mut value = json!({"port": 80});
+        let lens = Lens::new("port");
+        assert_eq!(
+            lens.set_validated(&mut value, json!(8080), &schema()),
+            Ok(())
+        );
+        assert_eq!(value, json!({"port": 8080}));
+    }
+
+    #[test]
+    fn test_set_validated_rejected() {
+        let mut value = json!({"port": 80});
+        let lens = Lens::new("port");
+
+        let Err(ValidationError::Invalid(errors)) =
+            lens.set_validated(&mut value, json!("http"), &schema())
+        else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("/port"), "{}", errors[0]);
+        assert_eq!(value, json!({"port": 80}));
+
+        assert!(lens.set_validated(&mut value, json!(0), &schema()).is_err());
+        assert_eq!(value, json!({"port": 80}));
+    }
+
+    #[test]
+    fn test_set_validated_bad_schema() {
+        let mut value = json!({"port": 80});
+        let result = Lens::new("port").set_validated(&mut value, json!(1), &json!({"type": 1}));
+        assert!(matches!(result, Err(ValidationError::Schema(_))));
+        assert_eq!(value, json!({"port": 80}));
+    }
+}
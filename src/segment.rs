@@ -0,0 +1,69 @@
+use crate::{Lens, Select};
+
+/// A single concrete step into a document, as opposed to a [`Lens`] which
+/// may fan out over several values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+impl From<Segment> for Lens {
+    fn from(segment: Segment) -> Self {
+        match segment {
+            Segment::Field(field) => Lens::Field(field),
+            Segment::Index(index) => Lens::Index(index),
+        }
+    }
+}
+
+impl Select for Segment {
+    fn pipe(self, lens: Lens) -> Lens {
+        lens.select(Lens::from(self))
+    }
+}
+
+impl Lens {
+    /// Composes `segments` in order; an empty slice yields `Lens::Empty`.
+    pub fn from_segments(segments: &[Segment]) -> Lens {
+        segments.iter().cloned().fold(Lens::Empty, Lens::select)
+    }
+
+    /// The segments of a lens made only of `Field` and `Index` steps, the
+    /// inverse of [`Lens::from_segments`]. Returns `None` if the lens has
+    /// any other kind of segment.
+    pub fn segments(&self) -> Option<Vec<Segment>> {
+        self.parts()
+            .into_iter()
+            .map(|part| match part {
+                Lens::Field(field) => Some(Segment::Field(field.clone())),
+                Lens::Index(index) => Some(Segment::Index(*index)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_segments_round_trip() {
+        let lens = Lens::new("a").select(0).select("b");
+        let segments = lens.segments().unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Field("a".to_string()),
+                Segment::Index(0),
+                Segment::Field("b".to_string())
+            ]
+        );
+        assert_eq!(Lens::from_segments(&segments), lens);
+
+        assert_eq!(Lens::from_segments(&[]), Lens::Empty);
+        assert_eq!(Lens::Empty.segments(), Some(Vec::new()));
+        assert_eq!(Lens::new("a").each().segments(), None);
+    }
+}
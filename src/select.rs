@@ -1,12 +1,42 @@
-use crate::Lens;
+use std::fmt;
+
+use crate::{Lens, ParseError};
 
 pub trait Select {
     fn pipe(self, lens: Lens) -> Lens;
 }
 
+/// Like [`Select`], for segments that may be malformed.
+pub trait TrySelect {
+    fn try_pipe(self, lens: Lens) -> Result<Lens, SelectError>;
+}
+
+/// A segment passed to [`Lens::try_select`] that doesn't parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectError {
+    pub segment: String,
+    pub error: ParseError,
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid segment `{}`: {}", self.segment, self.error)
+    }
+}
+
+impl std::error::Error for SelectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Composing with `Lens::Empty` yields the other lens unchanged.
 impl Select for Lens {
     fn pipe(self, other: Lens) -> Lens {
-        Lens::Compose(Box::new(other), Box::new(self))
+        match (other, self) {
+            (Lens::Empty, lens) | (lens, Lens::Empty) => lens,
+            (first, second) => Lens::Compose(Box::new(first), Box::new(second)),
+        }
     }
 }
 
@@ -27,3 +57,47 @@ impl Select for usize {
         lens.select(Lens::Index(self))
     }
 }
+
+/// Parses the string with [`Lens::parse`], so `"a.b[0]"` selects three
+/// segments and `"[x]"` is an error.
+impl TrySelect for &str {
+    fn try_pipe(self, lens: Lens) -> Result<Lens, SelectError> {
+        match Lens::parse(self) {
+            Ok(segments) => Ok(segments
+                .parts()
+                .into_iter()
+                .cloned()
+                .fold(lens, Lens::select)),
+            Err(error) => Err(SelectError {
+                segment: self.to_string(),
+                error,
+            }),
+        }
+    }
+}
+
+impl TrySelect for Lens {
+    fn try_pipe(self, lens: Lens) -> Result<Lens, SelectError> {
+        Ok(lens.select(self))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_try_select() {
+        let lens = Lens::new("data").try_select("items[0]");
+        assert_eq!(lens, Ok(Lens::new("data").select("items").select(0)));
+        assert_eq!(
+            Lens::new("data").try_select(Lens::ForEach),
+            Ok(Lens::new("data").each())
+        );
+
+        let err = Lens::new("data").try_select("items[x]").unwrap_err();
+        assert_eq!(err.segment, "items[x]");
+        assert_eq!(err.error.position, 6);
+        assert!(Lens::new("data").try_select("items[0").is_err());
+    }
+}
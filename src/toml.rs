@@ -0,0 +1,243 @@
+use serde_json::{Map, Number, Value};
+use toml::Value as Toml;
+
+use crate::data::{self, find_key_ci, position, Data};
+use crate::Lens;
+
+/// Converts a TOML value to JSON. Datetimes become strings in their TOML
+/// notation. Returns `None` for non-finite floats, which JSON can't hold.
+pub fn toml_to_json(value: &Toml) -> Option<Value> {
+    let value = match value {
+        Toml::String(text) => Value::String(text.clone()),
+        Toml::Integer(n) => Value::from(*n),
+        Toml::Float(n) => Value::Number(Number::from_f64(*n)?),
+        Toml::Boolean(b) => Value::Bool(*b),
+        Toml::Datetime(datetime) => Value::String(datetime.to_string()),
+        Toml::Array(arr) => Value::Array(arr.iter().map(toml_to_json).collect::<Option<_>>()?),
+        Toml::Table(table) => {
+            let mut obj = Map::new();
+            for (key, value) in table {
+                obj.insert(key.clone(), toml_to_json(value)?);
+            }
+            Value::Object(obj)
+        }
+    };
+    Some(value)
+}
+
+/// Converts a JSON value to TOML. TOML has no `null`: keys holding one are
+/// left out of their table, and a `null` anywhere else makes the conversion
+/// fail with `None`, as do integers beyond the range of `i64`.
+pub fn json_to_toml(value: &Value) -> Option<Toml> {
+    let value = match value {
+        Value::Null => return None,
+        Value::Bool(b) => Toml::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => Toml::Integer(n),
+            None if n.is_f64() => Toml::Float(n.as_f64()?),
+            None => return None,
+        },
+        Value::String(text) => Toml::String(text.clone()),
+        Value::Array(arr) => Toml::Array(arr.iter().map(json_to_toml).collect::<Option<_>>()?),
+        Value::Object(obj) => {
+            let mut table = toml::Table::new();
+            for (key, value) in obj.iter().filter(|(_, value)| !value.is_null()) {
+                table.insert(key.clone(), json_to_toml(value)?);
+            }
+            Toml::Table(table)
+        }
+    };
+    Some(value)
+}
+
+impl Data for Toml {
+    fn step(&self, part: &Lens) -> Option<&Self> {
+        match (part, self) {
+            (Lens::Field(field), Toml::Table(table)) => table.get(field),
+            (Lens::FieldCI(field), Toml::Table(table)) => {
+                table.get(find_key_ci(table.keys().map(String::as_str), field)?)
+            }
+            (Lens::Index(index), Toml::Array(arr)) => arr.get(position(*index, arr.len())?),
+            _ => None,
+        }
+    }
+
+    fn step_mut(&mut self, part: &Lens) -> Option<&mut Self> {
+        match (part, self) {
+            (Lens::Field(field), Toml::Table(table)) => table.get_mut(field),
+            (Lens::FieldCI(field), Toml::Table(table)) => {
+                let key = find_key_ci(table.keys().map(String::as_str), field)?.to_string();
+                table.get_mut(&key)
+            }
+            (Lens::Index(index), Toml::Array(arr)) => {
+                let index = position(*index, arr.len())?;
+                arr.get_mut(index)
+            }
+            _ => None,
+        }
+    }
+
+    fn set_step(&mut self, part: &Lens, value: Self) {
+        match (part, self) {
+            (Lens::Field(field), Toml::Table(table)) => {
+                table.insert(field.clone(), value);
+            }
+            (Lens::FieldCI(field), Toml::Table(table)) => {
+                let key = find_key_ci(table.keys().map(String::as_str), field)
+                    .unwrap_or(field)
+                    .to_string();
+                table.insert(key, value);
+            }
+            (Lens::Index(index), Toml::Array(arr)) => {
+                if let Some(index) = position(*index, arr.len()) {
+                    arr[index] = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn delete_step(&mut self, part: &Lens) {
+        match (part, self) {
+            (Lens::Field(field), Toml::Table(table)) => {
+                table.remove(field);
+            }
+            (Lens::FieldCI(field), Toml::Table(table)) => {
+                if let Some(key) = find_key_ci(table.keys().map(String::as_str), field) {
+                    let key = key.to_string();
+                    table.remove(&key);
+                }
+            }
+            (Lens::Index(index), Toml::Array(arr)) => {
+                if let Some(index) = position(*index, arr.len()) {
+                    arr.remove(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn to_json(&self) -> Option<Value> {
+        toml_to_json(self)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        json_to_toml(value)
+    }
+}
+
+/// Lenses over TOML documents. Leading `Field`, `FieldCI` and `Index`
+/// segments are resolved on the TOML itself, so a lens made only of them
+/// reads and writes values as they are, datetimes included. Anything after
+/// them, such as a `ForEach`, goes through [`toml_to_json`] for the value
+/// they lead to: within that value datetimes become strings, and nothing
+/// happens if the result can't be converted back. The rest of the document
+/// is left untouched either way.
+///
+/// TOML has no `null`, so keys are removed with [`Lens::delete_toml`]
+/// rather than by writing `null`, and deleting the whole document doesn't
+/// change it.
+impl Lens {
+    /// Like [`Lens::get`] over a TOML document, with a multi-focus becoming
+    /// an array.
+    pub fn get_toml(&self, value: &Toml) -> Option<Toml> {
+        data::get(self, value)
+    }
+
+    /// Like [`Lens::set`] over a TOML document.
+    pub fn set_toml(&self, source: &mut Toml, target: Toml) {
+        data::set(self, source, target)
+    }
+
+    /// Like [`Lens::delete`] over a TOML document.
+    pub fn delete_toml(&self, source: &mut Toml) {
+        data::delete(self, source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn document() -> Toml {
+        Toml::Table(toml::from_str("[server]\nhost = \"localhost\"\nports = [80, 443]\n").unwrap())
+    }
+
+    #[test]
+    fn test_toml() {
+        let mut document = document();
+        let lens = Lens::parse("server.ports[1]").unwrap();
+        assert_eq!(lens.get_toml(&document), Some(Toml::Integer(443)));
+        assert_eq!(
+            Lens::parse("server.ports[*]").unwrap().get_toml(&document),
+            Some(Toml::Array(vec![Toml::Integer(80), Toml::Integer(443)]))
+        );
+
+        lens.set_toml(&mut document, Toml::Integer(8443));
+        Lens::parse("server.host")
+            .unwrap()
+            .delete_toml(&mut document);
+        assert_eq!(
+            toml::to_string(&document).unwrap(),
+            "[server]\nports = [80, 8443]\n"
+        );
+    }
+
+    #[test]
+    fn test_toml_unrepresentable() {
+        let mut document = document();
+        let lens = Lens::parse("server.host").unwrap();
+        lens.set_toml(&mut document, Toml::Boolean(true));
+        assert_eq!(lens.get_toml(&document), Some(Toml::Boolean(true)));
+
+        assert_eq!(json_to_toml(&json!({"a": [1, null]})), None);
+        lens.set_toml(&mut document, Toml::Float(f64::NAN));
+        assert!(matches!(lens.get_toml(&document), Some(Toml::Float(n)) if n.is_nan()));
+        let ports = Lens::parse("server.ports[*]").unwrap();
+        ports.set_toml(&mut document, Toml::Float(f64::NAN));
+        assert_eq!(
+            ports.get_toml(&document),
+            Some(Toml::Array(vec![Toml::Integer(80), Toml::Integer(443)]))
+        );
+
+        lens.set_toml(&mut document, Toml::from("localhost"));
+        let before = document.clone();
+        Lens::Empty.delete_toml(&mut document);
+        assert_eq!(document, before);
+    }
+
+    #[test]
+    fn test_toml_datetimes() {
+        let text = "[release]\nname = \"v1\"\ndate = 2024-03-01T10:00:00Z\n\n[[items]]\nn = 1\n";
+        let mut document = Toml::Table(toml::from_str(text).unwrap());
+        let date = Lens::parse("release.date").unwrap();
+        assert!(matches!(date.get_toml(&document), Some(Toml::Datetime(_))));
+
+        Lens::parse("release.name")
+            .unwrap()
+            .set_toml(&mut document, Toml::from("v2"));
+        Lens::parse("items[*].n")
+            .unwrap()
+            .set_toml(&mut document, Toml::Integer(2));
+        assert!(matches!(date.get_toml(&document), Some(Toml::Datetime(_))));
+        assert_eq!(
+            toml::to_string(&document).unwrap(),
+            "[[items]]\nn = 2\n\n[release]\ndate = 2024-03-01T10:00:00Z\nname = \"v2\"\n"
+        );
+
+        Lens::parse("items[*]").unwrap().delete_toml(&mut document);
+        assert_eq!(
+            Lens::new("items").get_toml(&document),
+            Some(Toml::Array(vec![]))
+        );
+        assert_eq!(
+            json_to_toml(&json!({"a": null, "b": 1})),
+            Some(Toml::Table(toml::Table::from_iter([(
+                "b".to_string(),
+                Toml::Integer(1)
+            )])))
+        );
+    }
+}
@@ -1,23 +1,270 @@
+use serde::ser::{Serialize, SerializeSeq, Serializer};
 use serde_json::Value;
 
-use crate::Lens;
+use crate::{Lens, LensError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum View<'a> {
     Borrow(&'a Value),
     BorrowVec(Vec<View<'a>>),
+    Owned(Value),
 }
 
 impl<'a> View<'a> {
     pub fn get(self, lens: &'a Lens) -> Option<Self> {
+        self.try_get(lens).ok()
+    }
+
+    pub fn try_get(self, lens: &'a Lens) -> Result<Self, LensError> {
         match self {
-            View::Borrow(value) => lens.get(value),
-            View::BorrowVec(values) => Some(View::BorrowVec(
+            View::Borrow(value) => lens.try_get(value),
+            View::BorrowVec(values) => Ok(View::BorrowVec(
                 values
                     .into_iter()
                     .filter_map(|value| value.get(lens))
                     .collect(),
             )),
+            View::Owned(value) => lens.try_get(&value).map(View::into_owned),
+        }
+    }
+
+    /// Applies `lens` to the focused values without consuming the view, and
+    /// clones the result like [`View::into_value`]. As with `get`, a
+    /// multi-focus keeps the leaves where `lens` resolves.
+    pub fn get_owned(&self, lens: &Lens) -> Option<Value> {
+        match self {
+            View::Borrow(value) => lens.get(value).map(View::into_value),
+            View::BorrowVec(values) => Some(Value::Array(
+                values
+                    .iter()
+                    .filter_map(|value| value.get_owned(lens))
+                    .collect(),
+            )),
+            View::Owned(value) => lens.get(value).map(View::into_value),
+        }
+    }
+
+    /// Whether `lens` resolves against the view, like [`Lens::exists`]. For
+    /// a multi-focus, whether it resolves against at least one leaf.
+    pub fn exists(&self, lens: &Lens) -> bool {
+        match self {
+            View::Borrow(value) => lens.exists(value),
+            View::BorrowVec(values) => values.iter().any(|value| value.exists(lens)),
+            View::Owned(value) => lens.exists(value),
+        }
+    }
+
+    /// Clones the focused values into a single `Value`, turning multi-focus
+    /// views into arrays.
+    pub fn into_value(self) -> Value {
+        match self {
+            View::Borrow(value) => value.clone(),
+            View::BorrowVec(values) => {
+                Value::Array(values.into_iter().map(View::into_value).collect())
+            }
+            View::Owned(value) => value,
+        }
+    }
+
+    /// Detaches the view from the source document by cloning every borrow.
+    pub fn into_owned<'b>(self) -> View<'b> {
+        match self {
+            View::Borrow(value) => View::Owned(value.clone()),
+            View::BorrowVec(values) => {
+                View::BorrowVec(values.into_iter().map(View::into_owned).collect())
+            }
+            View::Owned(value) => View::Owned(value),
+        }
+    }
+}
+
+/// Iterates the leaves of the view in order. The values are cloned, since
+/// the borrowed ones are tied to the source document.
+impl IntoIterator for View<'_> {
+    type Item = Value;
+    type IntoIter = std::vec::IntoIter<Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves.into_iter()
+    }
+}
+
+impl View<'_> {
+    /// Compares the focused values, regardless of whether they're borrowed
+    /// or owned.
+    pub(crate) fn same_values(&self, other: &View<'_>) -> bool {
+        match (self, other) {
+            (View::BorrowVec(a), View::BorrowVec(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same_values(b))
+            }
+            (View::BorrowVec(_), _) | (_, View::BorrowVec(_)) => false,
+            (a, b) => a.as_value() == b.as_value(),
+        }
+    }
+
+    /// Drops the `null` leaves of a multi-focus, `None` if the view itself
+    /// is a single `null`.
+    pub(crate) fn non_null(self) -> Option<Self> {
+        match self {
+            View::Borrow(Value::Null) | View::Owned(Value::Null) => None,
+            View::BorrowVec(views) => Some(View::BorrowVec(
+                views.into_iter().filter_map(View::non_null).collect(),
+            )),
+            view => Some(view),
+        }
+    }
+
+    /// The single focused value, borrowed or owned.
+    pub(crate) fn as_value(&self) -> Option<&Value> {
+        match self {
+            View::Borrow(value) => Some(value),
+            View::Owned(value) => Some(value),
+            View::BorrowVec(_) => None,
+        }
+    }
+
+    /// The number of leaves in the view, counting nested views through.
+    pub(crate) fn leaf_count(&self) -> usize {
+        match self {
+            View::BorrowVec(values) => values.iter().map(View::leaf_count).sum(),
+            _ => 1,
         }
     }
+
+    fn collect_leaves(self, leaves: &mut Vec<Value>) {
+        match self {
+            View::Borrow(value) => leaves.push(value.clone()),
+            View::BorrowVec(values) => values
+                .into_iter()
+                .for_each(|value| value.collect_leaves(leaves)),
+            View::Owned(value) => leaves.push(value),
+        }
+    }
+}
+
+/// Serializes the focused values in place, writing multi-focus views as
+/// arrays like [`View::into_value`] but without cloning anything.
+impl Serialize for View<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            View::Borrow(value) => value.serialize(serializer),
+            View::BorrowVec(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            View::Owned(value) => value.serialize(serializer),
+        }
+    }
+}
+
+/// A [`View`] that owns its values, so it outlives the source document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedView {
+    Value(Value),
+    Vec(Vec<OwnedView>),
+}
+
+impl OwnedView {
+    /// Iterates the leaves of the view in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Value> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves.into_iter()
+    }
+
+    /// Turns multi-focus views into arrays, like [`View::into_value`].
+    pub fn into_value(self) -> Value {
+        match self {
+            OwnedView::Value(value) => value,
+            OwnedView::Vec(views) => {
+                Value::Array(views.into_iter().map(OwnedView::into_value).collect())
+            }
+        }
+    }
+
+    fn collect_leaves<'a>(&'a self, leaves: &mut Vec<&'a Value>) {
+        match self {
+            OwnedView::Value(value) => leaves.push(value),
+            OwnedView::Vec(views) => views.iter().for_each(|view| view.collect_leaves(leaves)),
+        }
+    }
+}
+
+impl From<View<'_>> for OwnedView {
+    fn from(view: View<'_>) -> Self {
+        match view {
+            View::Borrow(value) => OwnedView::Value(value.clone()),
+            View::BorrowVec(views) => {
+                OwnedView::Vec(views.into_iter().map(OwnedView::from).collect())
+            }
+            View::Owned(value) => OwnedView::Value(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_into_iter() {
+        let value = json!([{"a": 1}, {"b": 2}, {"a": [3]}]);
+        let lens = Lens::foreach().select("a");
+
+        let mut values = Vec::new();
+        for value in lens.get(&value).unwrap() {
+            values.push(value);
+        }
+        assert_eq!(values, vec![json!(1), json!([3])]);
+
+        let nested = json!([[1, 2], [3]]);
+        let values: Vec<_> = Lens::foreach()
+            .each()
+            .get(&nested)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(values, vec![json!(1), json!(2), json!(3)]);
+    }
+
+    #[test]
+    fn test_owned_view() {
+        fn names(lens: &Lens) -> Option<OwnedView> {
+            let value = json!({"users": [{"name": "a"}, {"name": "b"}]});
+            lens.get_owned_view(&value)
+        }
+
+        let view = names(&Lens::new("users").each().select("name")).unwrap();
+        assert_eq!(
+            view.iter().collect::<Vec<_>>(),
+            vec![&json!("a"), &json!("b")]
+        );
+        assert_eq!(view.into_value(), json!(["a", "b"]));
+        assert_eq!(names(&Lens::new("missing")), None);
+    }
+
+    #[test]
+    fn test_view_get_owned() {
+        let value = json!({"users": [{"name": "a", "id": 1}, {"id": 2}, {"name": "c"}]});
+        let users = Lens::new("users").each();
+        let view = users.get(&value).unwrap();
+        assert_eq!(view.get_owned(&Lens::new("name")), Some(json!(["a", "c"])));
+        assert_eq!(view.get_owned(&Lens::new("id")), Some(json!([1, 2])));
+        assert!(view.exists(&Lens::new("name")));
+        assert!(!view.exists(&Lens::new("nope")));
+        assert!(!View::BorrowVec(Vec::new()).exists(&Lens::Empty));
+
+        let lens = Lens::new("users").select(0);
+        let first = lens.get(&value).unwrap();
+        assert_eq!(first.get_owned(&Lens::new("id")), Some(json!(1)));
+        assert!(!first.exists(&Lens::new("missing")));
+        assert_eq!(first.get_owned(&Lens::new("missing")), None);
+    }
 }
@@ -0,0 +1,244 @@
+use serde_json::{Map, Number, Value};
+use serde_yaml::Value as Yaml;
+
+use crate::data::{self, find_key_ci, position, Data};
+use crate::Lens;
+
+/// Converts a YAML value to JSON. Tags are dropped in favour of the tagged
+/// value, and numeric or boolean keys become strings. Returns `None` for
+/// anything JSON can't hold: keys that are sequences, mappings or `null`,
+/// and non-finite floats.
+pub fn yaml_to_json(value: &Yaml) -> Option<Value> {
+    let value = match value {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(*b),
+        Yaml::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => Value::from(n),
+            (_, Some(n)) => Value::from(n),
+            _ => Value::Number(Number::from_f64(n.as_f64()?)?),
+        },
+        Yaml::String(text) => Value::String(text.clone()),
+        Yaml::Sequence(seq) => Value::Array(seq.iter().map(yaml_to_json).collect::<Option<_>>()?),
+        Yaml::Mapping(mapping) => {
+            let mut obj = Map::new();
+            for (key, value) in mapping {
+                let key = match key {
+                    Yaml::String(key) => key.clone(),
+                    Yaml::Number(n) => n.to_string(),
+                    Yaml::Bool(b) => b.to_string(),
+                    _ => return None,
+                };
+                obj.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(obj)
+        }
+        Yaml::Tagged(tagged) => yaml_to_json(&tagged.value)?,
+    };
+    Some(value)
+}
+
+/// Converts a JSON value to YAML. Every JSON value has a YAML counterpart.
+pub fn json_to_yaml(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Bool(*b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => Yaml::from(n),
+            (_, Some(n)) => Yaml::from(n),
+            _ => Yaml::from(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => Yaml::String(text.clone()),
+        Value::Array(arr) => Yaml::Sequence(arr.iter().map(json_to_yaml).collect()),
+        Value::Object(obj) => Yaml::Mapping(
+            obj.iter()
+                .map(|(key, value)| (Yaml::String(key.clone()), json_to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Steps see through tags, so a tagged mapping or sequence keeps its tag
+/// when something inside it is written.
+impl Data for Yaml {
+    fn step(&self, part: &Lens) -> Option<&Self> {
+        match (part, untag(self)) {
+            (Lens::Field(field), Yaml::Mapping(mapping)) => mapping.get(field.as_str()),
+            (Lens::FieldCI(field), Yaml::Mapping(mapping)) => {
+                mapping.get(find_key_ci(mapping.keys().filter_map(Yaml::as_str), field)?)
+            }
+            (Lens::Index(index), Yaml::Sequence(seq)) => seq.get(position(*index, seq.len())?),
+            _ => None,
+        }
+    }
+
+    fn step_mut(&mut self, part: &Lens) -> Option<&mut Self> {
+        match (part, untag_mut(self)) {
+            (Lens::Field(field), Yaml::Mapping(mapping)) => mapping.get_mut(field.as_str()),
+            (Lens::FieldCI(field), Yaml::Mapping(mapping)) => {
+                let key = find_key_ci(mapping.keys().filter_map(Yaml::as_str), field)?.to_string();
+                mapping.get_mut(key.as_str())
+            }
+            (Lens::Index(index), Yaml::Sequence(seq)) => {
+                let index = position(*index, seq.len())?;
+                seq.get_mut(index)
+            }
+            _ => None,
+        }
+    }
+
+    fn set_step(&mut self, part: &Lens, value: Self) {
+        match (part, untag_mut(self)) {
+            (Lens::Field(field), Yaml::Mapping(mapping)) => {
+                mapping.insert(Yaml::String(field.clone()), value);
+            }
+            (Lens::FieldCI(field), Yaml::Mapping(mapping)) => {
+                let key = find_key_ci(mapping.keys().filter_map(Yaml::as_str), field)
+                    .unwrap_or(field)
+                    .to_string();
+                mapping.insert(Yaml::String(key), value);
+            }
+            (Lens::Index(index), Yaml::Sequence(seq)) => {
+                if let Some(index) = position(*index, seq.len()) {
+                    seq[index] = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn delete_step(&mut self, part: &Lens) {
+        match (part, untag_mut(self)) {
+            (Lens::Field(field), Yaml::Mapping(mapping)) => {
+                mapping.shift_remove(field.as_str());
+            }
+            (Lens::FieldCI(field), Yaml::Mapping(mapping)) => {
+                if let Some(key) = find_key_ci(mapping.keys().filter_map(Yaml::as_str), field) {
+                    let key = key.to_string();
+                    mapping.shift_remove(key.as_str());
+                }
+            }
+            (Lens::Index(index), Yaml::Sequence(seq)) => {
+                if let Some(index) = position(*index, seq.len()) {
+                    seq.remove(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn to_json(&self) -> Option<Value> {
+        yaml_to_json(self)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(json_to_yaml(value))
+    }
+}
+
+fn untag(value: &Yaml) -> &Yaml {
+    match value {
+        Yaml::Tagged(tagged) => untag(&tagged.value),
+        value => value,
+    }
+}
+
+fn untag_mut(value: &mut Yaml) -> &mut Yaml {
+    match value {
+        Yaml::Tagged(tagged) => untag_mut(&mut tagged.value),
+        value => value,
+    }
+}
+
+/// Lenses over YAML documents. Leading `Field`, `FieldCI` and `Index`
+/// segments are resolved on the YAML itself, so a lens made only of them
+/// reads and writes nodes as they are, tags included. Anything after them,
+/// such as a `ForEach`, goes through [`yaml_to_json`] for the node they lead
+/// to: within that node tags are dropped and numeric and boolean keys become
+/// strings, and nothing happens if it can't be converted. The rest of the
+/// document is left untouched either way. Aliases have already been
+/// expanded by the parser, and are written out in full.
+impl Lens {
+    /// Like [`Lens::get`] over a YAML document, with a multi-focus becoming
+    /// a sequence.
+    pub fn get_yaml(&self, value: &Yaml) -> Option<Yaml> {
+        data::get(self, value)
+    }
+
+    /// Like [`Lens::set`] over a YAML document.
+    pub fn set_yaml(&self, source: &mut Yaml, target: Yaml) {
+        data::set(self, source, target)
+    }
+
+    /// Like [`Lens::delete`] over a YAML document.
+    pub fn delete_yaml(&self, source: &mut Yaml) {
+        data::delete(self, source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_yaml() {
+        let mut document: Yaml = serde_yaml::from_str("a:\n  b: [1, two]\n  c: 1.5\n").unwrap();
+        let lens = Lens::parse("a.b[0]").unwrap();
+        assert_eq!(lens.get_yaml(&document), Some(Yaml::from(1)));
+        assert_eq!(
+            Lens::parse("a.c").unwrap().get_yaml(&document),
+            Some(Yaml::from(1.5))
+        );
+
+        lens.set_yaml(&mut document, Yaml::String("one".to_string()));
+        assert_eq!(
+            serde_yaml::to_string(&document).unwrap(),
+            "a:\n  b:\n  - one\n  - two\n  c: 1.5\n"
+        );
+    }
+
+    #[test]
+    fn test_yaml_to_json() {
+        let tagged: Yaml = serde_yaml::from_str("1: !tag x\ntrue: y\n").unwrap();
+        assert_eq!(yaml_to_json(&tagged), Some(json!({"1": "x", "true": "y"})));
+        let nan: Yaml = serde_yaml::from_str("a: [.nan]\n").unwrap();
+        assert_eq!(yaml_to_json(&nan), None);
+        let complex_key: Yaml = serde_yaml::from_str("[a]: 1\n").unwrap();
+        assert_eq!(yaml_to_json(&complex_key), None);
+
+        let value = json!({"a": [null, true, -1, 2.5, "s"]});
+        assert_eq!(yaml_to_json(&json_to_yaml(&value)), Some(value));
+    }
+
+    #[test]
+    fn test_yaml_preserves_untouched_nodes() {
+        let text = "meta: !Version 2\n[a, b]: complex\nitems:\n- n: 1\n- n: 2\nconfig: !Config\n  name: x\n";
+        let mut document: Yaml = serde_yaml::from_str(text).unwrap();
+
+        Lens::new("config").set_yaml(&mut document, Yaml::from(1));
+        Lens::new("items")
+            .each()
+            .select("n")
+            .set_yaml(&mut document, Yaml::from(0));
+        assert_eq!(
+            serde_yaml::to_string(&document).unwrap(),
+            "meta: !Version 2\n? - a\n  - b\n: complex\nitems:\n- n: 0\n- n: 0\nconfig: 1\n"
+        );
+
+        let mut document: Yaml = serde_yaml::from_str(text).unwrap();
+        let name = Lens::new("config").select("name");
+        name.set_yaml(&mut document, Yaml::from("y"));
+        assert_eq!(name.get_yaml(&document), Some(Yaml::from("y")));
+        assert!(matches!(
+            Lens::new("config").get_yaml(&document),
+            Some(Yaml::Tagged(tagged)) if tagged.tag == "Config"
+        ));
+        Lens::new("meta").delete_yaml(&mut document);
+        assert_eq!(Lens::new("meta").get_yaml(&document), None);
+        assert_eq!(
+            Lens::new("items").each().select("n").get_yaml(&document),
+            Some(Yaml::Sequence(vec![Yaml::from(1), Yaml::from(2)]))
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Applies arbitrary lenses to arbitrary documents and checks that no
+//! combination panics.
+
+use proptest::prelude::*;
+use serde_json::{Map, Value};
+use tailcall_lens::{IndexPolicy, JsonType, Lens, Modify, Operation, Transform};
+
+fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just("a".to_string()),
+        Just("b".to_string()),
+        "[a-cA-C]{0,2}"
+    ]
+}
+
+fn index() -> impl Strategy<Value = usize> {
+    prop_oneof![
+        0..4usize,
+        Just(Lens::LAST),
+        Just(usize::MAX - 1),
+        Just(1 << 40),
+        Just(isize::MAX as usize)
+    ]
+}
+
+fn json_type() -> impl Strategy<Value = JsonType> {
+    prop_oneof![
+        Just(JsonType::Bool),
+        Just(JsonType::Number),
+        Just(JsonType::String)
+    ]
+}
+
+fn document() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        prop_oneof![
+            "\\PC{0,4}",
+            Just("{\"a\": [1, {\"b\": 2}]}".to_string()),
+            Just("eyJhIjogMX0=".to_string()),
+            Just("42".to_string()),
+            Just("true".to_string()),
+        ]
+        .prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::vec((key(), inner), 0..4)
+                .prop_map(|entries| Value::Object(Map::from_iter(entries))),
+        ]
+    })
+}
+
+fn lens() -> impl Strategy<Value = Lens> {
+    let leaf = prop_oneof![
+        key().prop_map(Lens::Field),
+        key().prop_map(Lens::FieldCI),
+        index().prop_map(Lens::Index),
+        Just(Lens::ForEach),
+        Just(Lens::Wildcard),
+        Just(Lens::Length),
+        (key(), any::<i64>()).prop_map(|(field, id)| Lens::ById {
+            field,
+            value: Value::from(id % 4)
+        }),
+        (key(), prop::collection::vec(0..4i64, 0..3)).prop_map(|(field, ids)| Lens::ByIds {
+            field,
+            values: ids.into_iter().map(Value::from).collect()
+        }),
+        "[ab*?]{0,3}".prop_map(Lens::Glob),
+        index().prop_map(Lens::Take),
+        index().prop_map(Lens::Skip),
+        (key(), 0..4usize).prop_map(|(key, max_depth)| Lens::DescendLimited { key, max_depth }),
+        Just(Lens::Empty),
+    ];
+    leaf.prop_recursive(4, 16, 3, |inner| {
+        let nested = vec![
+            (inner.clone(), inner.clone())
+                .prop_map(|(first, second)| Lens::Compose(Box::new(first), Box::new(second)))
+                .boxed(),
+            prop::collection::vec(inner.clone(), 0..3)
+                .prop_map(Lens::Coalesce)
+                .boxed(),
+            prop::collection::vec(inner.clone(), 0..3)
+                .prop_map(Lens::Union)
+                .boxed(),
+            inner
+                .clone()
+                .prop_map(|inner| Lens::Embedded(Box::new(inner)))
+                .boxed(),
+            (inner.clone(), json_type())
+                .prop_map(|(inner, ty)| Lens::Cast(Box::new(inner), ty))
+                .boxed(),
+            inner
+                .clone()
+                .prop_map(|inner| inner.map(|value| Value::String(value.to_string())))
+                .boxed(),
+            (inner.clone(), document(), inner.clone())
+                .prop_map(|(condition, expected, inner)| Lens::Guard {
+                    condition: Box::new(condition),
+                    expected,
+                    inner: Box::new(inner),
+                })
+                .boxed(),
+        ];
+        #[cfg(feature = "base64")]
+        let nested = {
+            let mut nested = nested;
+            nested.push(
+                inner
+                    .prop_map(|inner| Lens::Base64(Box::new(inner)))
+                    .boxed(),
+            );
+            nested
+        };
+        prop::strategy::Union::new(nested)
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_get_does_not_panic(lens in lens(), value in document()) {
+        let _ = lens.get(&value);
+        let _ = lens.try_get(&value);
+        let _ = lens.resolve(&value);
+        let _ = lens.iter_matches(&value).count();
+        let _ = lens.exists(&value);
+        let _ = lens.compile().get(&value);
+        let _ = lens.to_string();
+    }
+
+    #[test]
+    fn test_set_does_not_panic(lens in lens(), value in document(), target in document()) {
+        lens.set(&mut value.clone(), target.clone());
+        lens.compile().set(&mut value.clone(), target.clone());
+        lens.merge(&mut value.clone(), target.clone());
+        lens.delete(&mut value.clone());
+        if let Some(Modify::BorrowMut(leaf)) = lens.get_mut(&mut value.clone()) {
+            *leaf = target.clone();
+        }
+        let _ = lens.get_all_mut(&mut value.clone());
+        lens.truncate_str(&mut value.clone(), 1);
+        lens.swap(&mut value.clone(), 0, usize::MAX);
+        lens.increment(&mut value.clone(), -1.0);
+        let _ = lens.pop(&mut value.clone());
+        let _ = lens.apply(&mut value.clone(), Operation::Over(Transform::Increment(1.0)));
+        let _ = lens.apply(&mut value.clone(), Operation::Over(Transform::Cast(JsonType::Number)));
+    }
+
+    #[test]
+    fn test_path_writes_do_not_panic(lens in lens(), value in document(), target in document()) {
+        lens.ensure(&mut value.clone(), target.clone());
+        lens.ensure(&mut Value::Null, target.clone());
+        for policy in [IndexPolicy::Ignore, IndexPolicy::Append, IndexPolicy::Fill] {
+            lens.set_with_policy(&mut value.clone(), target.clone(), policy);
+        }
+        lens.set_through_null(&mut value.clone(), target.clone());
+        lens.set_through_null(&mut Value::Null, target.clone());
+        lens.walk_mut(&mut value.clone(), |_, leaf| *leaf = target.clone());
+        lens.walk_mut(&mut value.clone(), |_, leaf| *leaf = Value::Null);
+        let _ = lens.set_observed(&mut value.clone(), target.clone());
+    }
+}